@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A calendar date, such as `2026-10-18`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// Make a date, if it exists.
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let days_in_month = match month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => return None,
+        };
+        if day >= 1 && day <= days_in_month && year <= 9999 {
+            Some(Date { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    /// The number of days since 1970-01-01.
+    fn days_since_epoch(self) -> i64 {
+        // The year starting in March, so that leap days are at the end.
+        let (year, month) = if self.month <= 2 {
+            (i64::from(self.year) - 1, i64::from(self.month) + 9)
+        } else {
+            (i64::from(self.year), i64::from(self.month) - 3)
+        };
+        let day_of_year = (153 * month + 2) / 5 + i64::from(self.day) - 1;
+        year * 365 + year.div_euclid(4) - year.div_euclid(100) + year.div_euclid(400) + day_of_year
+            - 719_468
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// A moment in time, as a date and time with a time zone offset, such as
+/// `2026-10-18T12:00:00Z` or `2026-10-18T14:00:00.5+02:00`.
+///
+/// Timestamps compare by the moment they refer to, regardless of the offset
+/// they are written in.
+#[derive(Clone, Copy, Debug)]
+pub struct Timestamp {
+    pub date: Date,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+
+    /// The offset from UTC, in minutes.
+    pub offset: i16,
+}
+
+impl Timestamp {
+    /// The number of seconds since 1970-01-01T00:00:00Z, and the nanoseconds.
+    pub fn unix_time(&self) -> (i64, u32) {
+        let seconds = self.date.days_since_epoch() * 86_400
+            + i64::from(self.hour) * 3600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second)
+            - i64::from(self.offset) * 60;
+        (seconds, self.nanosecond)
+    }
+}
+
+impl PartialEq for Timestamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.unix_time() == other.unix_time()
+    }
+}
+
+impl Eq for Timestamp {}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.unix_time().cmp(&other.unix_time())
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}",
+            self.date, self.hour, self.minute, self.second
+        )?;
+        if self.nanosecond != 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        match self.offset {
+            0 => write!(f, "Z"),
+            offset => write!(
+                f,
+                "{}{:02}:{:02}",
+                if offset < 0 { '-' } else { '+' },
+                offset.abs() / 60,
+                offset.abs() % 60
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn timestamp(date: Date, hour: u8, offset: i16) -> Timestamp {
+        Timestamp {
+            date,
+            hour,
+            minute: 0,
+            second: 0,
+            nanosecond: 0,
+            offset,
+        }
+    }
+
+    #[test]
+    fn dates() {
+        assert!(Date::new(2024, 2, 29).is_some());
+        assert!(Date::new(2026, 2, 29).is_none());
+        assert!(Date::new(1900, 2, 29).is_none());
+        assert!(Date::new(2000, 2, 29).is_some());
+        assert!(Date::new(2026, 13, 1).is_none());
+        assert!(Date::new(2026, 4, 31).is_none());
+        assert_eq!(Date::new(1970, 1, 1).unwrap().days_since_epoch(), 0);
+        assert_eq!(Date::new(2000, 3, 1).unwrap().days_since_epoch(), 11_017);
+        assert_eq!(Date::new(1969, 12, 31).unwrap().days_since_epoch(), -1);
+    }
+
+    #[test]
+    fn timestamps() {
+        let date = Date::new(2026, 10, 18).unwrap();
+        let utc = timestamp(date, 12, 0);
+        assert_eq!(utc.unix_time(), (1_792_324_800, 0));
+        assert_eq!(utc, timestamp(date, 14, 120));
+        assert!(utc < timestamp(date, 12, -60));
+        assert_eq!(utc.to_string(), "2026-10-18T12:00:00Z");
+        assert_eq!(
+            Timestamp {
+                nanosecond: 500_000_000,
+                offset: -330,
+                ..utc
+            }
+            .to_string(),
+            "2026-10-18T12:00:00.5-05:30"
+        );
+    }
+}
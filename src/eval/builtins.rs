@@ -0,0 +1,420 @@
+//! The standard library, available as `std:name`.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+use super::function::{Call, Function};
+use super::ops::binary_op;
+use super::value::Value;
+use error::Error;
+use operator::BinaryOperator;
+
+type Builtin = for<'e, 'a> fn(&Call<'e, 'a>, Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>>;
+
+/// The name, minimum and maximum number of arguments, and implementation of
+/// each builtin function.
+const BUILTINS: &[(&str, usize, Option<usize>, Builtin)] = &[
+    ("len", 1, Some(1), len),
+    ("keys", 1, Some(1), keys),
+    ("values", 1, Some(1), values),
+    ("map", 2, Some(2), map),
+    ("filter", 2, Some(2), filter),
+    ("sort", 1, Some(1), sort),
+    ("join", 1, Some(2), join),
+    ("split", 2, Some(2), split),
+    ("replace", 3, Some(3), replace),
+    ("upper", 1, Some(1), upper),
+    ("lower", 1, Some(1), lower),
+    ("min", 1, None, min),
+    ("max", 1, None, max),
+    ("range", 1, Some(3), range),
+    ("contains", 2, Some(2), contains),
+    ("format", 1, None, format),
+    ("to_string", 1, Some(1), to_string),
+    ("to_int", 1, Some(1), to_int),
+];
+
+/// All builtin functions, with their names.
+pub fn functions() -> impl Iterator<Item = (&'static str, Function)> {
+    BUILTINS
+        .iter()
+        .map(|&(name, min, max, f)| (name, Function::new(&format!("std:{}", name), min, max, f)))
+}
+
+fn len<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let len = match &arguments[0] {
+        Value::List(list) => list.len(),
+        Value::String(s) => s.chars().count(),
+        Value::Object(object) => object.len(),
+        other => return Err(call.type_error(0, "a list, string or object", other)),
+    };
+    Ok(Value::Integer(len as i64))
+}
+
+fn keys<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    match &arguments[0] {
+        Value::Object(object) => Ok(Value::List(
+            object
+                .fields()
+                .iter()
+                .filter(|field| !field.local)
+                .map(|field| Value::String(field.key.clone()))
+                .collect(),
+        )),
+        other => Err(call.type_error(0, "an object", other)),
+    }
+}
+
+fn values<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    match arguments[0] {
+        Value::Object(object) => {
+            let mut values = Vec::new();
+            for (index, field) in object.fields().iter().enumerate() {
+                if !field.local {
+                    values.push(
+                        call.evaluator
+                            .field(object, index, call.op_source.as_bytes())?,
+                    );
+                }
+            }
+            Ok(Value::List(values))
+        }
+        ref other => Err(call.type_error(0, "an object", other)),
+    }
+}
+
+/// Check the arguments of a function taking a list and a function.
+fn list_and_function<'a>(
+    call: &Call<'_, 'a>,
+    arguments: Vec<Value<'a>>,
+) -> Result<(Vec<Value<'a>>, Function), Error<'a>> {
+    let mut arguments = arguments.into_iter();
+    match (arguments.next().unwrap(), arguments.next().unwrap()) {
+        (Value::List(list), Value::Function(f)) => Ok((list, f)),
+        (Value::List(_), other) => Err(call.type_error(1, "a function", &other)),
+        (other, _) => Err(call.type_error(0, "a list", &other)),
+    }
+}
+
+fn map<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let (list, f) = list_and_function(call, arguments)?;
+    list.into_iter()
+        .map(|value| f.call(call.evaluator, call.op_source, vec![value]))
+        .collect::<Result<_, _>>()
+        .map(Value::List)
+}
+
+fn filter<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let (list, f) = list_and_function(call, arguments)?;
+    let mut result = Vec::new();
+    for value in list {
+        match f.call(call.evaluator, call.op_source, vec![value.clone()])? {
+            Value::Bool(true) => result.push(value),
+            Value::Bool(false) => {}
+            other => {
+                return Err(call.error(format!(
+                    "`{}' expects `{}' to give a boolean, got {}",
+                    call.name,
+                    f.name(),
+                    other.type_name()
+                )))
+            }
+        }
+    }
+    Ok(Value::List(result))
+}
+
+/// Compare two values with `<`, as used by `sort`, `min` and `max`.
+fn compare<'a>(call: &Call<'_, 'a>, a: &Value<'a>, b: &Value<'a>) -> Result<Ordering, Error<'a>> {
+    let less = |a: &Value<'a>, b: &Value<'a>| match binary_op(
+        BinaryOperator::Less,
+        call.op_source,
+        a.clone(),
+        b.clone(),
+    ) {
+        Ok(Value::Bool(less)) => Ok(less),
+        _ => Err(call.error(format!(
+            "`{}' cannot compare {} and {}",
+            call.name,
+            a.type_name(),
+            b.type_name()
+        ))),
+    };
+    Ok(if less(a, b)? {
+        Ordering::Less
+    } else if less(b, a)? {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    })
+}
+
+fn sort<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let mut list = match arguments.into_iter().next().unwrap() {
+        Value::List(list) => list,
+        other => return Err(call.type_error(0, "a list", &other)),
+    };
+    let mut result = Ok(());
+    list.sort_by(|a, b| match compare(call, a, b) {
+        Ok(ordering) => ordering,
+        Err(e) => {
+            result = Err(e);
+            Ordering::Equal
+        }
+    });
+    result.map(|_| Value::List(list))
+}
+
+/// Take the string arguments of a function that only takes strings.
+fn strings<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Vec<String>, Error<'a>> {
+    arguments
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| match value {
+            Value::String(s) => Ok(s),
+            other => Err(call.type_error(index, "a string", &other)),
+        })
+        .collect()
+}
+
+fn join<'a>(call: &Call<'_, 'a>, mut arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let separator = match arguments.get(1) {
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => return Err(call.type_error(1, "a string", other)),
+        None => String::new(),
+    };
+    let list = match arguments.swap_remove(0) {
+        Value::List(list) => list,
+        other => return Err(call.type_error(0, "a list of strings", &other)),
+    };
+    let mut result = String::new();
+    for (i, value) in list.into_iter().enumerate() {
+        match value {
+            Value::String(s) => {
+                if i != 0 {
+                    result += &separator;
+                }
+                result += &s;
+            }
+            _ => return Err(call.error(format!("`{}' expects a list of strings", call.name))),
+        }
+    }
+    Ok(Value::String(result))
+}
+
+fn split<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let arguments = strings(call, arguments)?;
+    if arguments[1].is_empty() {
+        return Err(call.error(format!("`{}' expects a non-empty separator", call.name)));
+    }
+    Ok(Value::List(
+        arguments[0]
+            .split(&arguments[1][..])
+            .map(|s| Value::String(s.to_string()))
+            .collect(),
+    ))
+}
+
+fn replace<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let arguments = strings(call, arguments)?;
+    Ok(Value::String(
+        arguments[0].replace(&arguments[1][..], &arguments[2]),
+    ))
+}
+
+fn upper<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let arguments = strings(call, arguments)?;
+    Ok(Value::String(arguments[0].to_uppercase()))
+}
+
+fn lower<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let arguments = strings(call, arguments)?;
+    Ok(Value::String(arguments[0].to_lowercase()))
+}
+
+/// The smallest or largest of the arguments, or of the elements of a list if
+/// that's the only argument.
+fn extreme<'a>(
+    call: &Call<'_, 'a>,
+    arguments: Vec<Value<'a>>,
+    wanted: Ordering,
+) -> Result<Value<'a>, Error<'a>> {
+    let values = match <[_; 1]>::try_from(arguments) {
+        Ok([Value::List(list)]) => list,
+        Ok([value]) => vec![value],
+        Err(arguments) => arguments,
+    };
+    let mut values = values.into_iter();
+    let mut result = values
+        .next()
+        .ok_or_else(|| call.error(format!("`{}' of an empty list", call.name)))?;
+    for value in values {
+        if compare(call, &value, &result)? == wanted {
+            result = value;
+        }
+    }
+    Ok(result)
+}
+
+fn min<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    extreme(call, arguments, Ordering::Less)
+}
+
+fn max<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    extreme(call, arguments, Ordering::Greater)
+}
+
+/// The longest list `range` makes, such that a typo can't exhaust memory.
+const MAX_RANGE_LENGTH: i128 = 1_000_000;
+
+/// `range(end)`, `range(start, end)` or `range(start, end, step)`.
+fn range<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let mut integers = Vec::new();
+    for (index, value) in arguments.iter().enumerate() {
+        match *value {
+            Value::Integer(i) => integers.push(i),
+            ref other => return Err(call.type_error(index, "an integer", other)),
+        }
+    }
+    let (start, end, step) = match integers[..] {
+        [end] => (0, end, 1),
+        [start, end] => (start, end, 1),
+        [start, end, step] => (start, end, step),
+        _ => unreachable!(),
+    };
+    if step == 0 {
+        return Err(call.error(format!("`{}' expects a non-zero step", call.name)));
+    }
+    let (distance, step_size) = (i128::from(end) - i128::from(start), i128::from(step));
+    let length = if distance.signum() == step_size.signum() {
+        (distance.abs() + step_size.abs() - 1) / step_size.abs()
+    } else {
+        0
+    };
+    if length > MAX_RANGE_LENGTH {
+        return Err(call.error(format!(
+            "`{}' would give {} elements, more than the maximum of {}",
+            call.name, length, MAX_RANGE_LENGTH
+        )));
+    }
+    let mut list = Vec::with_capacity(length as usize);
+    let mut i = start;
+    while (step > 0 && i < end) || (step < 0 && i > end) {
+        list.push(Value::Integer(i));
+        i = match i.checked_add(step) {
+            Some(i) => i,
+            None => break,
+        };
+    }
+    Ok(Value::List(list))
+}
+
+fn contains<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let reference = call.op_source.as_bytes();
+    let found = match (&arguments[0], &arguments[1]) {
+        (Value::List(list), needle) => {
+            let mut found = false;
+            for value in list {
+                if call.evaluator.equal(value, needle, reference)? {
+                    found = true;
+                    break;
+                }
+            }
+            found
+        }
+        (Value::String(s), Value::String(needle)) => s.contains(&needle[..]),
+        (Value::Object(object), Value::String(key)) => object.index_of(key).is_some(),
+        (Value::String(_), other) | (Value::Object(_), other) => {
+            return Err(call.type_error(1, "a string", other))
+        }
+        (other, _) => return Err(call.type_error(0, "a list, string or object", other)),
+    };
+    Ok(Value::Bool(found))
+}
+
+/// Write down a value for in a string: strings as they are, and everything
+/// else as it would be written in an expression.
+fn display<'a>(call: &Call<'_, 'a>, value: Value<'a>) -> Result<String, Error<'a>> {
+    match value {
+        Value::String(s) => Ok(s),
+        value => {
+            call.evaluator.force(&value, call.op_source.as_bytes())?;
+            Ok(value.to_string())
+        }
+    }
+}
+
+/// `format("{} and {}", a, b)`, where `{{` and `}}` stand for `{` and `}`.
+fn format<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let mut arguments = arguments.into_iter();
+    let template = match arguments.next().unwrap() {
+        Value::String(s) => s,
+        other => return Err(call.type_error(0, "a string", &other)),
+    };
+    let n_arguments = arguments.len();
+    let mut result = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                let value = arguments.next().ok_or_else(|| {
+                    call.error(format!(
+                        "`{}' got more `{{}}' than the {} values given",
+                        call.name, n_arguments
+                    ))
+                })?;
+                result += &display(call, value)?;
+            }
+            ('{', _) | ('}', _) => {
+                return Err(call.error(format!(
+                    "`{}' expects `{}' to be doubled or part of `{{}}'",
+                    call.name, c
+                )))
+            }
+            _ => result.push(c),
+        }
+    }
+    if arguments.len() != 0 {
+        return Err(call.error(format!(
+            "`{}' got {} values, but fewer `{{}}'",
+            call.name, n_arguments
+        )));
+    }
+    Ok(Value::String(result))
+}
+
+fn to_string<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let value = arguments.into_iter().next().unwrap();
+    display(call, value).map(Value::String)
+}
+
+fn to_int<'a>(call: &Call<'_, 'a>, arguments: Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> {
+    let cannot_convert = |value: &Value| {
+        call.error(format!(
+            "`{}' cannot convert {} to an integer",
+            call.name, value
+        ))
+    };
+    match &arguments[0] {
+        &Value::Integer(i) => Ok(Value::Integer(i)),
+        &Value::Bool(b) => Ok(Value::Integer(b as i64)),
+        value @ &Value::Double(d) => {
+            if d.is_finite() && d >= i64::MIN as f64 && d < i64::MAX as f64 {
+                Ok(Value::Integer(d as i64))
+            } else {
+                Err(cannot_convert(value))
+            }
+        }
+        value @ Value::String(s) => s
+            .trim()
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| cannot_convert(value)),
+        other => Err(call.type_error(0, "a number, boolean or string", other)),
+    }
+}
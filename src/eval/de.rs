@@ -0,0 +1,212 @@
+//! Deserializing evaluated values with serde.
+
+use std::error;
+use std::fmt;
+
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Unexpected, Visitor};
+
+use super::value::Value;
+
+/// An error while deserializing a value.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Error {
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error {
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Deserialize a value, such as one given by `Evaluator::evaluate`.
+///
+/// Durations deserialize into `std::time::Duration`, and byte sizes into
+/// integers counting the bytes. Dates and timestamps deserialize as RFC 3339
+/// strings, which is what date and time libraries generally accept.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, Error> {
+    T::deserialize(value)
+}
+
+fn visit_map<'de, K, V, I>(entries: I, visitor: V) -> Result<V::Value, Error>
+where
+    K: IntoDeserializer<'de, Error>,
+    V: Visitor<'de>,
+    I: Iterator<Item = (K, Value<'de>)>,
+{
+    let mut map = MapDeserializer::new(entries);
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+impl<'de> de::Deserializer<'de> for Value<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Integer(i) => visitor.visit_i64(i),
+            Value::Double(d) => visitor.visit_f64(d),
+            Value::Duration(d) => {
+                // This is how serde represents a `std::time::Duration`.
+                let fields = vec![
+                    ("secs", Value::Integer(d.as_secs() as i64)),
+                    ("nanos", Value::Integer(i64::from(d.subsec_nanos()))),
+                ];
+                visit_map(fields.into_iter(), visitor)
+            }
+            Value::ByteSize(b) => visitor.visit_u64(b),
+            Value::Date(d) => visitor.visit_string(d.to_string()),
+            Value::Timestamp(t) => visitor.visit_string(t.to_string()),
+            Value::String(s) => visitor.visit_string(s),
+            Value::List(list) => {
+                let mut seq = SeqDeserializer::new(list.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Object(object) => {
+                let fields: Vec<_> = object.iter().map(|(k, v)| (k.to_string(), v)).collect();
+                visit_map(fields.into_iter(), visitor)
+            }
+            Value::Function(_) => Err(de::Error::invalid_type(
+                Unexpected::Other("function"),
+                &visitor,
+            )),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Enums are either a string naming the variant, or an object with a
+    /// single field named after the variant.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::String(s) => visitor.visit_enum(s.into_deserializer()),
+            Value::Object(object) if object.len() == 1 => {
+                let fields: Vec<_> = object.iter().map(|(k, v)| (k.to_string(), v)).collect();
+                visitor.visit_enum(MapAccessDeserializer::new(MapDeserializer::new(
+                    fields.into_iter(),
+                )))
+            }
+            value => Err(de::Error::invalid_type(
+                Unexpected::Other(value.type_name()),
+                &"a string or an object with one field",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use super::*;
+    use eval::{Arena, Evaluator};
+    use parse::Parser;
+
+    fn deserialize<T: DeserializeOwned>(source: &str) -> Result<T, String> {
+        let expr = Parser::new(source.as_bytes())
+            .parse_document()
+            .map_err(|e| e.message.message)?;
+        let arena = Arena::new();
+        let evaluator = Evaluator::new(&arena);
+        let value = evaluator.evaluate(&expr).map_err(|e| e.message.message)?;
+        from_value(value).map_err(|e| e.message)
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            deserialize::<BTreeMap<String, Vec<i64>>>("a = [1, 2]; b = []"),
+            Ok(
+                vec![("a".to_string(), vec![1, 2]), ("b".to_string(), vec![])]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        assert_eq!(
+            deserialize::<(bool, Option<String>, Option<f64>)>("[true, null, 1.5]"),
+            Ok((true, None, Some(1.5)))
+        );
+        assert_eq!(
+            deserialize::<u8>("256"),
+            Err("invalid value: integer `256`, expected u8".to_string())
+        );
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(
+            deserialize::<BTreeMap<String, Duration>>("a = 30s; b = 1.5m; c = 250ms + 1us"),
+            Ok(vec![
+                ("a".to_string(), Duration::from_secs(30)),
+                ("b".to_string(), Duration::from_secs(90)),
+                ("c".to_string(), Duration::from_nanos(250_001_000)),
+            ]
+            .into_iter()
+            .collect())
+        );
+        assert_eq!(
+            deserialize::<Vec<u64>>("[64KiB, 1.5GB, 2 * 3B]"),
+            Ok(vec![65536, 1_500_000_000, 6])
+        );
+        assert_eq!(
+            deserialize::<Vec<String>>("[2026-10-18, 2026-10-18T12:00:00.5+02:00]"),
+            Ok(vec![
+                "2026-10-18".to_string(),
+                "2026-10-18T12:00:00.5+02:00".to_string()
+            ])
+        );
+        assert_eq!(
+            deserialize::<Duration>("30"),
+            Err("invalid type: integer `30`, expected struct Duration".to_string())
+        );
+    }
+}
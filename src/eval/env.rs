@@ -0,0 +1,232 @@
+//! Reading environment variables, with `env("NAME")`, `env.NAME` or
+//! `env("NAME", default)`.
+
+use std::collections::HashMap;
+use std::env::{self, VarError};
+
+use super::function::{Call, Function};
+use super::value::Value;
+use error::Error;
+use expression::{Expression, Literal};
+use parse::Parser;
+
+/// Where `env` gets the environment variables from.
+#[derive(Clone, Debug)]
+pub enum Environment {
+    /// The environment of the process.
+    Process,
+
+    /// A fixed set of variables, such that evaluation is reproducible.
+    Fixed(HashMap<String, String>),
+
+    /// No environment variables at all: using `env` is an error.
+    Disabled,
+}
+
+/// The `env` function.
+///
+/// Without a default, the variable must be set, and it is given as a string.
+/// `env.NAME` is short for `env("NAME")`. With a default, the variable is
+/// converted to the type of the default, such as an integer or a duration. A
+/// `null` default gives a string if the variable is set.
+pub(super) fn function() -> Function {
+    Function::new("env", 1, Some(2), |call, mut arguments| {
+        let default = if arguments.len() == 2 {
+            arguments.pop()
+        } else {
+            None
+        };
+        let name = match arguments.pop().unwrap() {
+            Value::String(name) => name,
+            other => return Err(call.type_error(0, "a string", &other)),
+        };
+        match (variable(call, &name)?, default) {
+            (Some(value), Some(default)) => convert(call, &name, value, &default),
+            (Some(value), None) => Ok(Value::String(value)),
+            (None, Some(default)) => Ok(default),
+            (None, None) => Err(not_set(call, &name)),
+        }
+    })
+    .with_fields(|call, name| match variable(call, name)? {
+        Some(value) => Ok(Value::String(value)),
+        None => Err(not_set(call, name)),
+    })
+}
+
+/// Get the value of a variable, if it is set.
+fn variable<'a>(call: &Call<'_, 'a>, name: &str) -> Result<Option<String>, Error<'a>> {
+    match &call.evaluator.environment {
+        Environment::Process => match env::var(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(VarError::NotPresent) => Ok(None),
+            Err(VarError::NotUnicode(_)) => Err(call.error(format!(
+                "environment variable `{}' is not valid UTF-8",
+                name
+            ))),
+        },
+        Environment::Fixed(variables) => Ok(variables.get(name).cloned()),
+        Environment::Disabled => Err(call.error("environment variables are disabled".to_string())),
+    }
+}
+
+fn not_set<'a>(call: &Call<'_, 'a>, name: &str) -> Error<'a> {
+    call.error(format!("environment variable `{}' is not set", name))
+}
+
+/// Convert the value of a variable to the type of `default`.
+fn convert<'a>(
+    call: &Call<'_, 'a>,
+    name: &str,
+    text: String,
+    default: &Value<'a>,
+) -> Result<Value<'a>, Error<'a>> {
+    let value = match default {
+        Value::Null | Value::String(_) => return Ok(Value::String(text)),
+        Value::Bool(_) => match &text.trim().to_ascii_lowercase()[..] {
+            "true" | "yes" | "on" | "1" => Some(Value::Bool(true)),
+            "false" | "no" | "off" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Value::Integer(_)
+        | Value::Double(_)
+        | Value::Duration(_)
+        | Value::ByteSize(_)
+        | Value::Date(_)
+        | Value::Timestamp(_) => match (literal(text.trim()), default) {
+            (Some(Value::Integer(i)), Value::Double(_)) => Some(Value::Double(i as f64)),
+            (Some(value), _) if value.type_name() == default.type_name() => Some(value),
+            _ => None,
+        },
+        other => {
+            return Err(call.type_error(
+                1,
+                "a string, number, boolean, duration, byte size, date, timestamp or null",
+                other,
+            ))
+        }
+    };
+    let type_name = default.type_name();
+    let article = if type_name.starts_with(|c| "aeiou".contains(c)) {
+        "an"
+    } else {
+        "a"
+    };
+    value.ok_or_else(|| {
+        call.error(format!(
+            "environment variable `{}' should be {} {}, got {}",
+            name,
+            article,
+            type_name,
+            Value::String(text)
+        ))
+    })
+}
+
+/// Parse a number, duration, byte size, date or timestamp literal.
+fn literal<'a>(text: &str) -> Option<Value<'a>> {
+    let expr = Parser::new(text.as_bytes()).parse_document().ok()?;
+    match expr {
+        Expression::Literal { value, .. } => match value {
+            Literal::Integer(i) => Some(Value::Integer(i)),
+            Literal::Double(d) => Some(Value::Double(d)),
+            Literal::Duration(d) => Some(Value::Duration(d)),
+            Literal::ByteSize(b) => Some(Value::ByteSize(b)),
+            Literal::Date(d) => Some(Value::Date(d)),
+            Literal::Timestamp(t) => Some(Value::Timestamp(t)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use eval::test::eval_using;
+
+    fn eval(source: &str, environment: Environment) -> Result<String, String> {
+        eval_using(source, |evaluator, expr| {
+            evaluator.environment = environment;
+            evaluator.evaluate(expr)
+        })
+    }
+
+    fn fixed(source: &str) -> Result<String, String> {
+        let variables = vec![
+            ("PORT", "9090"),
+            ("DEBUG", "Yes"),
+            ("TIMEOUT", "1.5s"),
+            ("RATIO", "2"),
+            ("NAME", "web"),
+            ("CONTROL", "\u{1}"),
+        ];
+        let variables = variables
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        eval(source, Environment::Fixed(variables))
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(
+            fixed("a = env(\"PORT\", 8080); b = env(\"HOST\", \"localhost\"); c = env(\"NAME\")"),
+            Ok("{ a = 9090, b = \"localhost\", c = \"web\" }".to_string())
+        );
+        assert_eq!(
+            fixed("a = env(\"DEBUG\", false); b = env(\"TIMEOUT\", 30s); c = env(\"RATIO\", 0.5); d = env(\"HOST\", null)"),
+            Ok("{ a = true, b = 1500ms, c = 2.0, d = null }".to_string())
+        );
+        assert_eq!(
+            fixed("a = env.PORT; b = env.NAME + \"-1\""),
+            Ok("{ a = \"9090\", b = \"web-1\" }".to_string())
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            fixed("env(\"HOST\")"),
+            Err("environment variable `HOST' is not set".to_string())
+        );
+        assert_eq!(
+            fixed("env(\"NAME\", 80)"),
+            Err("environment variable `NAME' should be an integer, got \"web\"".to_string())
+        );
+        assert_eq!(
+            fixed("env.HOST"),
+            Err("environment variable `HOST' is not set".to_string())
+        );
+        assert_eq!(
+            fixed("env(\"CONTROL\", 1)"),
+            Err("environment variable `CONTROL' should be an integer, got \"\\u0001\"".to_string())
+        );
+        assert_eq!(
+            fixed("env(\"PORT\", 1s)"),
+            Err("environment variable `PORT' should be a duration, got \"9090\"".to_string())
+        );
+        assert_eq!(
+            fixed("env(\"PORT\", [])"),
+            Err("`env' expects a string, number, boolean, duration, byte size, date, timestamp or null as argument 2, got list".to_string())
+        );
+        assert_eq!(
+            eval("env(\"PORT\", 8080)", Environment::Disabled),
+            Err("environment variables are disabled".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn not_unicode() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = "CONFTAAL_TEST_NOT_UNICODE";
+        env::set_var(name, OsStr::from_bytes(b"\xFF"));
+        assert_eq!(
+            eval("env(\"CONFTAAL_TEST_NOT_UNICODE\")", Environment::Process),
+            Err("environment variable `CONFTAAL_TEST_NOT_UNICODE' is not valid UTF-8".to_string())
+        );
+        env::remove_var(name);
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::value::Value;
+use super::Evaluator;
+use error::{error, Error};
+
+type Implementation =
+    dyn for<'e, 'a> Fn(&Call<'e, 'a>, Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>>;
+
+type FieldImplementation = dyn for<'e, 'a> Fn(&Call<'e, 'a>, &str) -> Result<Value<'a>, Error<'a>>;
+
+/// A function that can be called from an expression, such as `std:len`.
+///
+/// Functions don't borrow from the document they are used in, such that they
+/// can be shared between evaluations.
+#[derive(Clone)]
+pub struct Function {
+    name: String,
+    min_arguments: usize,
+    max_arguments: Option<usize>,
+    implementation: Rc<Implementation>,
+    fields: Option<Rc<FieldImplementation>>,
+}
+
+/// The context a function is called in.
+pub struct Call<'e, 'a> {
+    pub evaluator: &'e Evaluator<'a>,
+
+    /// The `(` of the call, or the name of the field for `function.name`,
+    /// which errors point at.
+    pub op_source: &'a str,
+
+    /// The name of the function that is called.
+    pub name: &'e str,
+}
+
+impl Function {
+    /// Make a function taking between `min_arguments` and `max_arguments`
+    /// arguments, or any number above `min_arguments` if `max_arguments` is
+    /// `None`.
+    ///
+    /// The number of arguments is checked before `implementation` is called.
+    pub fn new<F>(
+        name: &str,
+        min_arguments: usize,
+        max_arguments: Option<usize>,
+        implementation: F,
+    ) -> Self
+    where
+        F: for<'e, 'a> Fn(&Call<'e, 'a>, Vec<Value<'a>>) -> Result<Value<'a>, Error<'a>> + 'static,
+    {
+        Function {
+            name: name.to_string(),
+            min_arguments,
+            max_arguments,
+            implementation: Rc::new(implementation),
+            fields: None,
+        }
+    }
+
+    /// Let `function.name` give a value, such as `env.PORT`.
+    pub fn with_fields<F>(mut self, fields: F) -> Self
+    where
+        F: for<'e, 'a> Fn(&Call<'e, 'a>, &str) -> Result<Value<'a>, Error<'a>> + 'static,
+    {
+        self.fields = Some(Rc::new(fields));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Call the function, with `op_source` being the `(` of the call.
+    pub fn call<'a>(
+        &self,
+        evaluator: &Evaluator<'a>,
+        op_source: &'a str,
+        arguments: Vec<Value<'a>>,
+    ) -> Result<Value<'a>, Error<'a>> {
+        let call = Call {
+            evaluator,
+            op_source,
+            name: &self.name,
+        };
+        let n = arguments.len();
+        if n < self.min_arguments || self.max_arguments.map_or(false, |max| n > max) {
+            let plural = |n| if n == 1 { "argument" } else { "arguments" };
+            let expected = match self.max_arguments {
+                Some(max) if max == self.min_arguments => format!("{} {}", max, plural(max)),
+                Some(max) => format!("{} to {} arguments", self.min_arguments, max),
+                None => format!(
+                    "at least {} {}",
+                    self.min_arguments,
+                    plural(self.min_arguments)
+                ),
+            };
+            return Err(call.error(format!("`{}' takes {}, got {}", self.name, expected, n)));
+        }
+        (self.implementation)(&call, arguments)
+    }
+
+    /// Whether `function.name` can be used, see `with_fields`.
+    pub fn has_fields(&self) -> bool {
+        self.fields.is_some()
+    }
+
+    /// Get `function.name`. Panics if the function has no fields.
+    pub fn field<'a>(
+        &self,
+        evaluator: &Evaluator<'a>,
+        name: &'a str,
+    ) -> Result<Value<'a>, Error<'a>> {
+        let call = Call {
+            evaluator,
+            op_source: name,
+            name: &self.name,
+        };
+        let fields = self.fields.as_ref().expect("function has no fields");
+        fields(&call, name)
+    }
+}
+
+impl<'e, 'a> Call<'e, 'a> {
+    /// An error pointing at the call.
+    pub fn error(&self, message: String) -> Error<'a> {
+        error(self.op_source.as_bytes(), message)
+    }
+
+    /// An error for an argument of the wrong type.
+    ///
+    /// `index` counts from zero.
+    pub fn type_error(&self, index: usize, expected: &str, got: &Value) -> Error<'a> {
+        self.error(format!(
+            "`{}' expects {} as argument {}, got {}",
+            self.name,
+            expected,
+            index + 1,
+            got.type_name()
+        ))
+    }
+}
+
+/// Functions are equal if they are the same function, such as two uses of
+/// `std:len`, not if they happen to do the same thing.
+impl PartialEq for Function {
+    fn eq(&self, other: &Function) -> bool {
+        Rc::ptr_eq(&self.implementation, &other.implementation)
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Function({})", self.name)
+    }
+}
@@ -0,0 +1,406 @@
+//! Evaluating other files, with `import("file.conftaal")`, and reading
+//! files with `import_json` and `import_str`.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str;
+
+use super::function::Function;
+use super::value::Value;
+use super::Evaluator;
+use error::{error, Error, Message};
+use parse::json::parse_json;
+
+/// How an imported file is turned into a value.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    Conftaal,
+    Json,
+    /// Plain text, which becomes a string.
+    Text,
+}
+
+/// A file given to `Evaluator::evaluate_file`, or an imported one.
+struct File<'a> {
+    /// The path the loader resolved it to.
+    path: PathBuf,
+
+    format: Format,
+
+    source: &'a [u8],
+
+    /// The value of the file, once it has been evaluated.
+    value: Option<Value<'a>>,
+}
+
+/// The files that have been loaded.
+#[derive(Default)]
+pub(super) struct Files<'a> {
+    files: RefCell<Vec<File<'a>>>,
+
+    /// The files that are being evaluated, innermost last, each with the
+    /// `(` of the import that loaded it, if any.
+    loading: RefCell<Vec<(usize, Option<&'a str>)>>,
+}
+
+/// Where in which file a diagnostic points.
+///
+/// Lines and columns count from 1.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path.display(), self.line, self.column)
+    }
+}
+
+/// The `import`, `import_json` and `import_str` functions.
+pub(super) fn functions() -> Vec<(&'static str, Function)> {
+    fn import(name: &'static str, format: Format) -> (&'static str, Function) {
+        let function = Function::new(name, 1, Some(1), move |call, arguments| {
+            match &arguments[0] {
+                Value::String(path) => {
+                    call.evaluator
+                        .load(Path::new(path), Some(call.op_source), format)
+                }
+                other => Err(call.type_error(0, "a string", other)),
+            }
+        });
+        (name, function)
+    }
+    vec![
+        import("import", Format::Conftaal),
+        import("import_json", Format::Json),
+        import("import_str", Format::Text),
+    ]
+}
+
+/// The contents of a text file, as a string.
+fn text(source: &[u8]) -> Result<Value<'_>, Error<'_>> {
+    match str::from_utf8(source) {
+        Ok(text) => Ok(Value::String(text.to_string())),
+        Err(e) => Err(error(
+            &source[e.valid_up_to()..][..1],
+            "invalid UTF-8".to_string(),
+        )),
+    }
+}
+
+/// Whether `inner` is a part of `outer`.
+pub(super) fn contains(outer: &[u8], inner: &[u8]) -> bool {
+    let outer = outer.as_ptr_range();
+    let inner = inner.as_ptr_range();
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+impl<'a> Evaluator<'a> {
+    /// Parse and evaluate a file, including all the fields of all objects in
+    /// it.
+    ///
+    /// The file and the files it imports are found and read by `loader`.
+    pub fn evaluate_file(&self, path: &Path) -> Result<Value<'a>, Error<'a>> {
+        self.load(path, None, Format::Conftaal)
+    }
+
+    /// Find the file, line and column that a location in a diagnostic
+    /// points at.
+    ///
+    /// Gives `None` if the location is not in a file that was loaded by
+    /// `evaluate_file` or `import`.
+    pub fn locate(&self, location: &[u8]) -> Option<Location> {
+        let files = self.files.files.borrow();
+        let file = files.iter().find(|f| contains(f.source, location))?;
+        let offset = location.as_ptr() as usize - file.source.as_ptr() as usize;
+        let before = &file.source[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        Some(Location {
+            path: file.path.clone(),
+            line: before.iter().filter(|&&b| b == b'\n').count() + 1,
+            column: String::from_utf8_lossy(&before[line_start..])
+                .chars()
+                .count()
+                + 1,
+        })
+    }
+
+    /// Load and evaluate a file, or give its value if it was loaded before.
+    ///
+    /// `import` is the `(` of the import that loads the file, if any. The
+    /// path is resolved relative to the file containing it.
+    fn load(
+        &self,
+        path: &Path,
+        import: Option<&'a str>,
+        format: Format,
+    ) -> Result<Value<'a>, Error<'a>> {
+        let load_error = |action: &str, path: &Path, e: io::Error| Error {
+            message: Message {
+                message: format!("cannot {} `{}': {}", action, path.display(), e),
+                location: import.map(str::as_bytes),
+            },
+            notes: vec![],
+        };
+        let from = import.and_then(|import| {
+            let files = self.files.files.borrow();
+            let file = files.iter().find(|f| contains(f.source, import.as_bytes()));
+            file.map(|file| file.path.clone())
+        });
+        let path = self
+            .loader
+            .resolve(path, from.as_deref())
+            .map_err(|e| load_error(if import.is_some() { "import" } else { "open" }, path, e))?;
+        let existing = self
+            .files
+            .files
+            .borrow()
+            .iter()
+            .position(|f| f.path == path && f.format == format);
+        let index = match existing {
+            Some(index) => {
+                if let Some(value) = &self.files.files.borrow()[index].value {
+                    return Ok(value.clone());
+                }
+                self.check_cycle(index, import)?;
+                index
+            }
+            None => {
+                let source = self
+                    .loader
+                    .load(&path)
+                    .map_err(|e| load_error("read", &path, e))?;
+                let source = self.arena.alloc_source(source);
+                let mut files = self.files.files.borrow_mut();
+                files.push(File {
+                    path,
+                    format,
+                    source,
+                    value: None,
+                });
+                files.len() - 1
+            }
+        };
+        let source = self.files.files.borrow()[index].source;
+        self.files.loading.borrow_mut().push((index, import));
+        let evaluate = |expr| self.evaluate(self.arena.alloc_expression(expr));
+        let result = match format {
+            Format::Conftaal => self.parser(source).parse_document().and_then(evaluate),
+            Format::Json => parse_json(source).and_then(evaluate),
+            Format::Text => text(source),
+        };
+        self.files.loading.borrow_mut().pop();
+        let mut files = self.files.files.borrow_mut();
+        match result {
+            Ok(value) => {
+                files[index].value = Some(value.clone());
+                Ok(value)
+            }
+            Err(mut e) => {
+                if let Some(import) = import {
+                    e.notes.push(Message {
+                        message: format!("`{}' is imported here", files[index].path.display()),
+                        location: Some(import.as_bytes()),
+                    });
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Give an error if the file is already being evaluated.
+    fn check_cycle(&self, index: usize, import: Option<&'a str>) -> Result<(), Error<'a>> {
+        let loading = self.files.loading.borrow();
+        let start = match loading.iter().position(|&(i, _)| i == index) {
+            Some(start) => start,
+            None => return Ok(()),
+        };
+        let files = self.files.files.borrow();
+        let chain: Vec<String> = loading[start..]
+            .iter()
+            .map(|&(i, _)| i)
+            .chain(Some(index))
+            .map(|i| format!("`{}'", files[i].path.display()))
+            .collect();
+        let message = format!("import cycle: {}", chain.join(" -> "));
+        Err(match import {
+            Some(import) => error(import.as_bytes(), message),
+            None => Error {
+                message: Message {
+                    message,
+                    location: None,
+                },
+                notes: vec![],
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    use eval::loader::{Memory, SearchPath};
+    use eval::{Arena, Evaluator};
+    use parse::Parser;
+
+    fn eval_file(path: &str) -> Result<String, Vec<String>> {
+        let arena = Arena::new();
+        let mut evaluator = Evaluator::new(&arena);
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+        evaluator.register("count", move || {
+            counter.set(counter.get() + 1);
+            counter.get()
+        });
+        let result = evaluator.evaluate_file(Path::new(path));
+        // Diagnostics as `file:line:column: message`.
+        let diagnostic = |message: &::error::Message| match message.location {
+            Some(location) => format!(
+                "{}: {}",
+                evaluator.locate(location).unwrap(),
+                message.message
+            ),
+            None => message.message.clone(),
+        };
+        result.map(|v| v.to_string()).map_err(|e| {
+            Some(&e.message)
+                .into_iter()
+                .chain(&e.notes)
+                .map(diagnostic)
+                .collect()
+        })
+    }
+
+    #[test]
+    fn imports() {
+        assert_eq!(
+            eval_file("tests/import/main.conftaal"),
+            Ok(
+                "{ name = \"app\", port = 8081, child = { base = 8080, calls = 1 }, same = true }"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn loaders() {
+        let mut memory = Memory::new();
+        memory.insert(
+            "config/main.conftaal",
+            "a = import(\"common.conftaal\").x\nb = import(\"../modules/net.conftaal\")",
+        );
+        memory.insert(
+            "config/common.conftaal",
+            "x = import(\"net.conftaal\").port",
+        );
+        memory.insert("modules/net.conftaal", "port = 80");
+        let arena = Arena::new();
+        let mut evaluator = Evaluator::new(&arena);
+        evaluator.loader = Box::new(SearchPath::new(memory, vec!["modules".into()]));
+        let result = evaluator.evaluate_file(Path::new("config/main.conftaal"));
+        assert_eq!(
+            result.map(|v| v.to_string()).map_err(|e| e.message.message),
+            Ok("{ a = 80, b = { port = 80 } }".to_string())
+        );
+        let result = evaluator.evaluate_file(Path::new("config/missing.conftaal"));
+        assert_eq!(
+            result.map(|v| v.to_string()).map_err(|e| e.message.message),
+            Err("cannot open `config/missing.conftaal': no such file".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symbolic_links() {
+        use std::os::unix::fs::symlink;
+        use std::{env, fs, process};
+
+        let directory = env::temp_dir().join(format!("conftaal-test-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("common.conftaal"), "calls = count()").unwrap();
+        symlink("common.conftaal", directory.join("link.conftaal")).unwrap();
+        fs::write(
+            directory.join("main.conftaal"),
+            "a = import(\"common.conftaal\").calls\nb = import(\"link.conftaal\").calls",
+        )
+        .unwrap();
+        let result = eval_file(directory.join("main.conftaal").to_str().unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+        // Paths are not canonicalized, so the link is a different file.
+        assert_eq!(result, Ok("{ a = 1, b = 2 }".to_string()));
+    }
+
+    #[test]
+    fn data_files() {
+        let mut memory = Memory::new();
+        memory.insert(
+            "main.conftaal",
+            "let data = import_json(\"data.json\")\nservers = data.servers\nport = (data { port = 9090 }).port\nmotd = import_str(\"motd.txt\")",
+        );
+        memory.insert(
+            "data.json",
+            "{\"servers\": [\"a\", \"b\"], \"port\": 8080, \"tls\": null}",
+        );
+        memory.insert("motd.txt", "Hello\n");
+        memory.insert("bad.json", "{\"a\": 1,\n \"a\": 2}");
+        memory.insert("bad.txt", &b"\xFF"[..]);
+        let arena = Arena::new();
+        let mut evaluator = Evaluator::new(&arena);
+        evaluator.loader = Box::new(memory);
+        let result = evaluator.evaluate_file(Path::new("main.conftaal"));
+        assert_eq!(
+            result.map(|v| v.to_string()).map_err(|e| e.message.message),
+            Ok("{ servers = [\"a\", \"b\"], port = 9090, motd = \"Hello\\n\" }".to_string())
+        );
+        let expr = Parser::new(b"[import_json(\"bad.json\"), import_str(\"bad.txt\")]")
+            .parse_document()
+            .unwrap();
+        let e = evaluator.evaluate(&expr).unwrap_err();
+        assert_eq!(e.message.message, "duplicate key `a'");
+        assert_eq!(
+            evaluator
+                .locate(e.message.location.unwrap())
+                .unwrap()
+                .to_string(),
+            "bad.json:2:2"
+        );
+        let expr = Parser::new(b"import_str(\"bad.txt\")")
+            .parse_document()
+            .unwrap();
+        let e = evaluator.evaluate(&expr).unwrap_err();
+        assert_eq!(e.message.message, "invalid UTF-8");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            eval_file("tests/import/cycle_a.conftaal"),
+            Err(vec![
+                "tests/import/cycle_b.conftaal:1:11: import cycle: `tests/import/cycle_a.conftaal' -> `tests/import/cycle_b.conftaal' -> `tests/import/cycle_a.conftaal'".to_string(),
+                "tests/import/cycle_a.conftaal:2:11: `tests/import/cycle_b.conftaal' is imported here".to_string(),
+            ])
+        );
+        assert_eq!(
+            eval_file("tests/import/uses_broken.conftaal"),
+            Err(vec![
+                "tests/import/broken.conftaal:2:9: unsupported operand types for `+': integer and string".to_string(),
+                "tests/import/uses_broken.conftaal:1:11: `tests/import/broken.conftaal' is imported here".to_string(),
+            ])
+        );
+        assert_eq!(
+            eval_file("tests/import/missing.conftaal").map_err(|e| e.len()),
+            Err(1)
+        );
+    }
+}
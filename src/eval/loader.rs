@@ -0,0 +1,185 @@
+//! Finding and reading the files that are imported.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Resolves the paths given to `import` to files, and reads them.
+pub trait Loader {
+    /// Find the file that `path` refers to when imported by the file at
+    /// `from`, or when given to `Evaluator::evaluate_file` if `from` is
+    /// `None`.
+    ///
+    /// Files are cached by the path this gives, which is also used in
+    /// diagnostics. So, a file should resolve to the same path regardless of
+    /// where it is imported from.
+    fn resolve(&self, path: &Path, from: Option<&Path>) -> io::Result<PathBuf>;
+
+    /// Read a file, given the path that `resolve` gave for it.
+    fn load(&self, path: &Path) -> io::Result<Vec<u8>>;
+}
+
+/// Remove `.` and `dir/..` from a path, without looking at the file system.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The path relative to the file it is imported from.
+fn relative(path: &Path, from: Option<&Path>) -> PathBuf {
+    let directory = from.and_then(Path::parent).unwrap_or_else(|| Path::new(""));
+    normalize(&directory.join(path))
+}
+
+fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no such file")
+}
+
+/// Loads files from the file system, relative to the file they are imported
+/// from.
+///
+/// Paths are normalized without resolving symbolic links, such that
+/// diagnostics show them the way they were written. As files are cached by
+/// their path, this means that a file that is reached both through a
+/// symbolic link and directly is evaluated twice.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FileSystem;
+
+impl Loader for FileSystem {
+    fn resolve(&self, path: &Path, from: Option<&Path>) -> io::Result<PathBuf> {
+        let path = relative(path, from);
+        if fs::metadata(&path)?.is_file() {
+            Ok(path)
+        } else {
+            Err(io::Error::new(io::ErrorKind::Other, "not a file"))
+        }
+    }
+
+    fn load(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+}
+
+/// Loads files from memory, such as files that are bundled with a program.
+#[derive(Clone, Default, Debug)]
+pub struct Memory {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Memory::default()
+    }
+
+    /// Add a file, or replace it if it already exists.
+    pub fn insert<P: AsRef<Path>, S: Into<Vec<u8>>>(&mut self, path: P, source: S) {
+        self.files.insert(normalize(path.as_ref()), source.into());
+    }
+}
+
+impl Loader for Memory {
+    fn resolve(&self, path: &Path, from: Option<&Path>) -> io::Result<PathBuf> {
+        let path = relative(path, from);
+        if self.files.contains_key(&path) {
+            Ok(path)
+        } else {
+            Err(not_found())
+        }
+    }
+
+    fn load(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files.get(path).cloned().ok_or_else(not_found)
+    }
+}
+
+/// Looks for files in a list of directories, if they are not found relative
+/// to the file they are imported from.
+///
+/// Paths starting with `./` or `../` are only looked up relative to the
+/// importing file.
+#[derive(Clone, Debug)]
+pub struct SearchPath<L> {
+    pub loader: L,
+    pub directories: Vec<PathBuf>,
+}
+
+impl<L: Loader> SearchPath<L> {
+    pub fn new(loader: L, directories: Vec<PathBuf>) -> Self {
+        SearchPath {
+            loader,
+            directories,
+        }
+    }
+}
+
+impl<L: Loader> Loader for SearchPath<L> {
+    fn resolve(&self, path: &Path, from: Option<&Path>) -> io::Result<PathBuf> {
+        let error = match self.loader.resolve(path, from) {
+            Ok(path) => return Ok(path),
+            Err(e) => e,
+        };
+        let explicitly_relative = matches!(
+            path.components().next(),
+            Some(Component::CurDir) | Some(Component::ParentDir)
+        );
+        if path.is_absolute() || explicitly_relative {
+            return Err(error);
+        }
+        self.directories
+            .iter()
+            .filter_map(|directory| self.loader.resolve(&directory.join(path), None).ok())
+            .next()
+            .ok_or(error)
+    }
+
+    fn load(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.loader.load(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalizing() {
+        let n = |path: &str| normalize(Path::new(path));
+        assert_eq!(n("a/./b/../c"), Path::new("a/c"));
+        assert_eq!(n("./a"), Path::new("a"));
+        assert_eq!(n("../a/.."), Path::new(".."));
+        assert_eq!(n("/../a"), Path::new("/a"));
+    }
+
+    #[test]
+    fn search_path() {
+        let mut memory = Memory::new();
+        memory.insert("app/main.conftaal", "");
+        memory.insert("app/local.conftaal", "");
+        memory.insert("lib/local.conftaal", "");
+        memory.insert("lib/std/net.conftaal", "");
+        let loader = SearchPath::new(memory, vec!["vendor".into(), "lib".into()]);
+        let from = Some(Path::new("app/main.conftaal"));
+        let resolve = |path: &str| loader.resolve(Path::new(path), from).ok();
+        assert_eq!(resolve("local.conftaal"), Some("app/local.conftaal".into()));
+        assert_eq!(
+            resolve("std/net.conftaal"),
+            Some("lib/std/net.conftaal".into())
+        );
+        assert_eq!(resolve("./std/net.conftaal"), None);
+        assert_eq!(resolve("missing.conftaal"), None);
+    }
+}
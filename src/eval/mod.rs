@@ -0,0 +1,1836 @@
+mod builtins;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod env;
+pub mod function;
+pub mod import;
+pub mod loader;
+pub mod native;
+mod ops;
+mod overrides;
+mod scope;
+pub mod value;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ptr;
+
+use self::env::Environment;
+use self::import::Files;
+use self::loader::{FileSystem, Loader};
+use self::native::NativeFunction;
+use self::ops::{binary_op, number_equal, unary_op};
+use self::scope::Scope;
+use self::value::{Definition, Field, ForceState, Object, Value};
+use error::{error, Error};
+use expression::{Clause, Expression, Literal, Op, Segment};
+use operator::BinaryOperator;
+use parse::{duplicate_key, local_and_field, Parser};
+
+/// Owns the objects created during evaluation.
+///
+/// Values refer to the objects in here, so the arena has to outlive them.
+#[derive(Default)]
+pub struct Arena<'a> {
+    // Boxed, such that the objects don't move when the Vec grows.
+    #[allow(clippy::vec_box)]
+    objects: RefCell<Vec<Box<Object<'a>>>>,
+
+    /// The source code and parsed expressions of imported files.
+    sources: RefCell<Vec<Box<[u8]>>>,
+    #[allow(clippy::vec_box)]
+    expressions: RefCell<Vec<Box<Expression<'a>>>>,
+}
+
+impl<'a> Arena<'a> {
+    pub fn new() -> Self {
+        Arena {
+            objects: RefCell::new(Vec::new()),
+            sources: RefCell::new(Vec::new()),
+            expressions: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn alloc_source(&'a self, source: Vec<u8>) -> &'a [u8] {
+        let mut sources = self.sources.borrow_mut();
+        sources.push(source.into_boxed_slice());
+        let source: *const [u8] = &**sources.last().unwrap();
+        // Just like the objects, the sources never move.
+        unsafe { &*source }
+    }
+
+    fn alloc_expression(&'a self, expression: Expression<'a>) -> &'a Expression<'a> {
+        let mut expressions = self.expressions.borrow_mut();
+        expressions.push(Box::new(expression));
+        let expression: *const Expression<'a> = &**expressions.last().unwrap();
+        unsafe { &*expression }
+    }
+
+    fn alloc(&'a self, object: Object<'a>) -> &'a Object<'a> {
+        let mut objects = self.objects.borrow_mut();
+        objects.push(Box::new(object));
+        let object: *const Object<'a> = &**objects.last().unwrap();
+        // This is fine, because the boxed objects never move and are only
+        // dropped together with the arena.
+        unsafe { &*object }
+    }
+}
+
+/// What to do when an object defines the same key more than once.
+///
+/// Keys that are not computed are already checked by the parser, so for
+/// `LastWins` and `Merge`, set `Parser::allow_duplicate_keys` as well. The
+/// evaluator does so itself for the files it loads.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DuplicateKeys {
+    /// Give an error.
+    Error,
+
+    /// Use the last definition.
+    LastWins,
+
+    /// Merge the definitions if they are all objects, recursively.
+    /// Otherwise, use the last definition.
+    Merge,
+}
+
+pub struct Evaluator<'a> {
+    arena: &'a Arena<'a>,
+    pub duplicate_keys: DuplicateKeys,
+
+    /// Finds and reads the files given to `evaluate_file` and `import`.
+    ///
+    /// Uses the file system by default.
+    pub loader: Box<dyn Loader>,
+
+    /// Where `env` gets environment variables from.
+    ///
+    /// Uses the environment of the process by default.
+    pub environment: Environment,
+
+    /// The values that can be accessed as `namespace:name`, by namespace.
+    ///
+    /// These are kept apart from the fields of objects, such that they don't
+    /// collide with the keys used in the configuration.
+    namespaces: HashMap<String, HashMap<String, Value<'a>>>,
+
+    /// The values that can be referred to by name from anywhere, unless a
+    /// field with the same name is in scope.
+    globals: HashMap<String, Value<'a>>,
+
+    /// The files that have been loaded, to evaluate each file only once.
+    files: Files<'a>,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(arena: &'a Arena<'a>) -> Self {
+        let mut evaluator = Evaluator {
+            arena,
+            duplicate_keys: DuplicateKeys::Error,
+            loader: Box::new(FileSystem),
+            environment: Environment::Process,
+            namespaces: HashMap::new(),
+            globals: HashMap::new(),
+            files: Files::default(),
+        };
+        for (name, function) in builtins::functions() {
+            evaluator.define("std", name, Value::Function(function));
+        }
+        evaluator
+            .globals
+            .insert("env".to_string(), Value::Function(env::function()));
+        for (name, function) in import::functions() {
+            evaluator
+                .globals
+                .insert(name.to_string(), Value::Function(function));
+        }
+        evaluator
+    }
+
+    /// Make `value` available to expressions as `namespace:name`.
+    pub fn define(&mut self, namespace: &str, name: &str, value: Value<'a>) {
+        self.namespaces
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(name.to_string(), value);
+    }
+
+    /// Make a Rust function available to expressions as `name`, or as a
+    /// member of a namespace if `name` is of the form `namespace:name`.
+    ///
+    /// The arguments are converted with `FromValue`. If the function returns
+    /// an `Err`, it is reported as an error at the call.
+    pub fn register<A, F: NativeFunction<A>>(&mut self, name: &str, function: F) {
+        let value = Value::Function(function.into_function(name));
+        match name.find(':') {
+            Some(colon) => self.define(&name[..colon], &name[colon + 1..], value),
+            None => {
+                self.globals.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    /// A parser for a file this evaluator loads itself, which allows duplicate
+    /// keys if `duplicate_keys` does.
+    fn parser(&self, source: &'a [u8]) -> Parser<'a> {
+        Parser {
+            allow_duplicate_keys: self.duplicate_keys != DuplicateKeys::Error,
+            ..Parser::new(source)
+        }
+    }
+
+    /// Evaluate an expression, including all the fields of all objects in it.
+    pub fn evaluate(&self, expr: &'a Expression<'a>) -> Result<Value<'a>, Error<'a>> {
+        let value = self.eval(expr, &Scope::new())?;
+        self.force(&value, expr.location())?;
+        Ok(value)
+    }
+
+    /// Evaluate all fields of all objects in the value.
+    ///
+    /// `reference` is the location that refers to the value, which is used in
+    /// the error when the value contains itself.
+    fn force(&self, value: &Value<'a>, reference: &'a [u8]) -> Result<(), Error<'a>> {
+        match value {
+            Value::List(list) => list.iter().try_for_each(|v| self.force(v, reference)),
+            Value::Object(object) => match object.force_state() {
+                ForceState::Unforced => {
+                    object.set_force_state(ForceState::Forcing);
+                    let result = self.force_fields(object);
+                    object.set_force_state(match result {
+                        Ok(()) => ForceState::Forced,
+                        Err(_) => ForceState::Unforced,
+                    });
+                    result
+                }
+                ForceState::Forcing => {
+                    self.find_recursion(value, reference, &mut Vec::new())?;
+                    Err(error(reference, "object contains itself".to_string()))
+                }
+                ForceState::Forced => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn force_fields(&self, object: &'a Object<'a>) -> Result<(), Error<'a>> {
+        for (index, field) in object.fields().iter().enumerate() {
+            if field.local {
+                continue;
+            }
+            let value = self.field(object, index, field.location)?;
+            self.force(&value, field.location)?;
+        }
+        Ok(())
+    }
+
+    /// Look for a field that is still being evaluated in a value that is
+    /// reached again while it is being forced.
+    ///
+    /// Such a field needs the value to be forced, which is a recursive
+    /// definition. This gives the error for that, pointing at `reference`.
+    fn find_recursion(
+        &self,
+        value: &Value<'a>,
+        reference: &'a [u8],
+        seen: &mut Vec<&'a Object<'a>>,
+    ) -> Result<(), Error<'a>> {
+        match value {
+            Value::List(list) => list
+                .iter()
+                .try_for_each(|v| self.find_recursion(v, reference, seen)),
+            Value::Object(object) => match object.force_state() {
+                ForceState::Forcing if !seen.iter().any(|o| ptr::eq(*o, *object)) => {
+                    seen.push(object);
+                    for (index, field) in object.fields().iter().enumerate() {
+                        if !field.local {
+                            let value = self.field(object, index, reference)?;
+                            self.find_recursion(&value, reference, seen)?;
+                        }
+                    }
+                    Ok(())
+                }
+                ForceState::Unforced => self.force(value, reference),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn eval(&self, expr: &'a Expression<'a>, scope: &Scope<'a>) -> Result<Value<'a>, Error<'a>> {
+        match expr {
+            Expression::Identifier(identifier) => self.lookup(identifier, scope),
+            Expression::Literal { value, .. } => self.eval_literal(value, scope),
+            Expression::Op {
+                op_source,
+                op: Op::UnaryOp { op, rhs },
+                ..
+            } => unary_op(*op, op_source, self.eval(rhs, scope)?),
+            Expression::Op {
+                op_source,
+                op:
+                    Op::BinaryOp {
+                        op: BinaryOperator::Colon,
+                        lhs,
+                        rhs,
+                    },
+                ..
+            } => self.namespace_member(lhs, op_source, rhs),
+            Expression::Op {
+                op_source,
+                op: Op::BinaryOp { op, lhs, rhs },
+                ..
+            } => {
+                let lhs_value = self.eval(lhs, scope)?;
+                match *op {
+                    BinaryOperator::Dot => self.dot(lhs_value, op_source, rhs),
+                    BinaryOperator::Index => self.index(lhs_value, op_source, rhs, scope),
+                    BinaryOperator::Call => match (lhs_value, self.eval(rhs, scope)?) {
+                        (Value::Function(function), Value::List(arguments)) => {
+                            function.call(self, op_source, arguments)
+                        }
+                        (other, _) => Err(error(
+                            op_source.as_bytes(),
+                            format!("cannot call a value of type {}", other.type_name()),
+                        )),
+                    },
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => {
+                        let lhs_value = boolean(lhs_value, op_source)?;
+                        if lhs_value == (*op == BinaryOperator::LogicalOr) {
+                            Ok(Value::Bool(lhs_value))
+                        } else {
+                            boolean(self.eval(rhs, scope)?, op_source).map(Value::Bool)
+                        }
+                    }
+                    BinaryOperator::Equal | BinaryOperator::Inequal => {
+                        let rhs_value = self.eval(rhs, scope)?;
+                        let equal = self.equal(&lhs_value, &rhs_value, op_source.as_bytes())?;
+                        Ok(Value::Bool(equal == (*op == BinaryOperator::Equal)))
+                    }
+                    BinaryOperator::Extend => match (lhs_value, self.eval(rhs, scope)?) {
+                        (Value::Object(a), Value::Object(b)) => {
+                            Ok(Value::Object(self.merge_objects(a, b, false)))
+                        }
+                        (other, _) => Err(error(
+                            op_source.as_bytes(),
+                            format!("cannot extend {}", other.type_name()),
+                        )),
+                    },
+                    op => match (op, lhs_value, self.eval(rhs, scope)?) {
+                        (BinaryOperator::Plus, Value::Object(a), Value::Object(b)) => {
+                            Ok(Value::Object(self.merge_objects(a, b, false)))
+                        }
+                        // Deep merge: fields that are objects on both sides
+                        // are merged recursively. Otherwise, `b` wins.
+                        (BinaryOperator::BitAnd, Value::Object(a), Value::Object(b)) => {
+                            Ok(Value::Object(self.merge_objects(a, b, true)))
+                        }
+                        (op, lhs_value, rhs_value) => {
+                            binary_op(op, op_source, lhs_value, rhs_value)
+                        }
+                    },
+                }
+            }
+            Expression::Op {
+                op:
+                    Op::Conditional {
+                        condition,
+                        then_branch,
+                        else_branch,
+                    },
+                ..
+            } => match self.eval(condition, scope)? {
+                Value::Bool(true) => self.eval(then_branch, scope),
+                Value::Bool(false) => self.eval(else_branch, scope),
+                other => Err(error(
+                    condition.location(),
+                    format!("expected boolean condition, got {}", other.type_name()),
+                )),
+            },
+        }
+    }
+
+    fn eval_literal(
+        &self,
+        literal: &'a Literal<'a>,
+        scope: &Scope<'a>,
+    ) -> Result<Value<'a>, Error<'a>> {
+        match literal {
+            &Literal::Null => Ok(Value::Null),
+            &Literal::Bool(b) => Ok(Value::Bool(b)),
+            &Literal::Integer(i) => Ok(Value::Integer(i)),
+            &Literal::Double(d) => Ok(Value::Double(d)),
+            &Literal::Duration(d) => Ok(Value::Duration(d)),
+            &Literal::ByteSize(b) => Ok(Value::ByteSize(b)),
+            &Literal::Date(d) => Ok(Value::Date(d)),
+            &Literal::Timestamp(t) => Ok(Value::Timestamp(t)),
+            Literal::String(s) => Ok(Value::String(s.to_string())),
+            Literal::Interpolation(segments) => {
+                let mut string = String::new();
+                for segment in segments {
+                    match segment {
+                        Segment::Text { value, .. } => string.push_str(value),
+                        Segment::Interpolation { source, expression } => {
+                            match self.eval(expression, scope)? {
+                                Value::String(s) => string.push_str(&s),
+                                value @ Value::Integer(_)
+                                | value @ Value::Double(_)
+                                | value @ Value::Duration(_)
+                                | value @ Value::ByteSize(_)
+                                | value @ Value::Date(_)
+                                | value @ Value::Timestamp(_)
+                                | value @ Value::Bool(_) => string.push_str(&value.to_string()),
+                                other => {
+                                    return Err(error(
+                                        source,
+                                        format!(
+                                            "cannot interpolate {} into a string",
+                                            other.type_name()
+                                        ),
+                                    ))
+                                }
+                            }
+                        }
+                    }
+                }
+                Ok(Value::String(string))
+            }
+            Literal::List(elements) => elements
+                .iter()
+                .map(|e| self.eval(e, scope))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+            Literal::Slice(..) => unreachable!("slices only appear in `[...]'"),
+            Literal::Object(keys, values, locals) => self.eval_object(keys, values, locals, scope),
+            Literal::ListComprehension(element, clauses) => {
+                let mut list = Vec::new();
+                self.eval_clauses(clauses, scope, &mut Vec::new(), &mut |scope, _| {
+                    list.push(self.eval(element, scope)?);
+                    Ok(())
+                })?;
+                Ok(Value::List(list))
+            }
+            Literal::ObjectComprehension(key, value, clauses) => {
+                let mut fields = Vec::new();
+                self.eval_clauses(clauses, scope, &mut Vec::new(), &mut |inner, variables| {
+                    let definition = Definition {
+                        expression: value,
+                        scope: scope.clone(),
+                        variables: variables.to_vec(),
+                        base: None,
+                    };
+                    let key_string = self.eval_key(key, inner)?;
+                    self.add_field(&mut fields, key_string, key.location(), false, definition)
+                })?;
+                Ok(Value::Object(self.arena.alloc(Object::new(fields))))
+            }
+        }
+    }
+
+    /// Evaluate the `for` and `if` clauses of a comprehension.
+    ///
+    /// `f` is called with the scope for every combination of values of the
+    /// variables for which all conditions hold, together with the objects
+    /// holding those variables.
+    fn eval_clauses<F>(
+        &self,
+        clauses: &'a [Clause<'a>],
+        scope: &Scope<'a>,
+        variables: &mut Vec<&'a Object<'a>>,
+        f: &mut F,
+    ) -> Result<(), Error<'a>>
+    where
+        F: FnMut(&Scope<'a>, &[&'a Object<'a>]) -> Result<(), Error<'a>>,
+    {
+        let (clause, rest) = match clauses.split_first() {
+            Some(split) => split,
+            None => return f(scope, variables),
+        };
+        match clause {
+            Clause::If(condition) => match self.eval(condition, scope)? {
+                Value::Bool(true) => self.eval_clauses(rest, scope, variables, f),
+                Value::Bool(false) => Ok(()),
+                other => Err(error(
+                    condition.location(),
+                    format!("expected boolean condition, got {}", other.type_name()),
+                )),
+            },
+            Clause::For {
+                variables: names,
+                iterable,
+            } => {
+                let items: Vec<Vec<Value<'a>>> = match (self.eval(iterable, scope)?, names.len()) {
+                    (Value::List(list), 1) => list.into_iter().map(|v| vec![v]).collect(),
+                    (Value::List(list), _) => list
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, v)| vec![Value::Integer(i as i64), v])
+                        .collect(),
+                    (Value::Object(object), n) => object
+                        .fields()
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, field)| !field.local)
+                        .map(|(i, field)| {
+                            let key = Value::String(field.key.clone());
+                            if n == 1 {
+                                Ok(vec![key])
+                            } else {
+                                Ok(vec![key, self.field(object, i, iterable.location())?])
+                            }
+                        })
+                        .collect::<Result<_, _>>()?,
+                    (other, _) => {
+                        return Err(error(
+                            iterable.location(),
+                            format!("cannot iterate over {}", other.type_name()),
+                        ))
+                    }
+                };
+                for values in items {
+                    let object = self.arena.alloc(Object::from_values(
+                        names
+                            .iter()
+                            .zip(values)
+                            .map(|(name, value)| (name.to_string(), name.as_bytes(), value))
+                            .collect(),
+                    ));
+                    variables.push(object);
+                    self.eval_clauses(rest, &scope.with_variables(object), variables, f)?;
+                    variables.pop();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Make an object out of the keys and values of an object literal.
+    ///
+    /// Only the keys are evaluated at this point, in the scope around the
+    /// object. The values are evaluated when they are needed.
+    fn eval_object(
+        &self,
+        keys: &'a [Box<Expression<'a>>],
+        values: &'a [Box<Expression<'a>>],
+        locals: &[bool],
+        scope: &Scope<'a>,
+    ) -> Result<Value<'a>, Error<'a>> {
+        let mut fields: Vec<Field<'a>> = Vec::with_capacity(keys.len());
+        for ((key_expr, value), &local) in keys.iter().zip(values.iter()).zip(locals) {
+            let key = self.eval_key(key_expr, scope)?;
+            let definition = Definition {
+                expression: value,
+                scope: scope.clone(),
+                variables: Vec::new(),
+                base: None,
+            };
+            self.add_field(&mut fields, key, key_expr.location(), local, definition)?;
+        }
+        Ok(Value::Object(self.arena.alloc(Object::new(fields))))
+    }
+
+    /// Evaluate the key of an object element.
+    fn eval_key(&self, key: &'a Expression<'a>, scope: &Scope<'a>) -> Result<String, Error<'a>> {
+        match key {
+            Expression::Literal {
+                value: Literal::String(key),
+                ..
+            } => Ok(key.to_string()),
+            _ => match self.eval(key, scope)? {
+                Value::String(key) => Ok(key),
+                other => Err(error(
+                    key.location(),
+                    format!("object key must be a string, not {}", other.type_name()),
+                )),
+            },
+        }
+    }
+
+    /// Add a field to the fields of a new object.
+    ///
+    /// If a field with the same key already exists, `duplicate_keys` decides
+    /// what happens.
+    fn add_field(
+        &self,
+        fields: &mut Vec<Field<'a>>,
+        key: String,
+        location: &'a [u8],
+        local: bool,
+        definition: Definition<'a>,
+    ) -> Result<(), Error<'a>> {
+        if let Some(field) = fields.iter_mut().find(|f| f.key == key) {
+            if field.local != local {
+                return Err(local_and_field(&key, location, field.location));
+            }
+            match self.duplicate_keys {
+                DuplicateKeys::Error => return Err(duplicate_key(&key, location, field.location)),
+                DuplicateKeys::LastWins => {
+                    field.location = location;
+                    field.local = local;
+                    field.definitions = vec![definition];
+                }
+                DuplicateKeys::Merge => {
+                    field.location = location;
+                    field.local = local;
+                    field.definitions.push(definition);
+                }
+            }
+            return Ok(());
+        }
+        fields.push(Field {
+            key,
+            location,
+            local,
+            definitions: vec![definition],
+        });
+        Ok(())
+    }
+
+    /// Make a new object with the fields of both objects.
+    ///
+    /// Fields that exist in both are merged using the definitions of both if
+    /// `merge_fields` is set. Otherwise, the field of `b` overrides that of `a`.
+    ///
+    /// Either way, all fields are evaluated in the context of the new object,
+    /// so fields of `a` that refer to a field overridden by `b` see the new
+    /// value.
+    fn merge_objects(
+        &self,
+        a: &'a Object<'a>,
+        b: &'a Object<'a>,
+        merge_fields: bool,
+    ) -> &'a Object<'a> {
+        let mut fields: Vec<Field<'a>> = a.fields().to_vec();
+        for field in b.fields() {
+            match fields.iter_mut().find(|f| f.key == field.key) {
+                Some(f) if merge_fields => {
+                    f.location = field.location;
+                    f.local = field.local;
+                    f.definitions.extend(field.definitions.iter().cloned());
+                }
+                Some(f) => *f = extended_field(field, a),
+                None if merge_fields => fields.push(field.clone()),
+                None => fields.push(extended_field(field, a)),
+            }
+        }
+        self.arena.alloc(Object::new(fields))
+    }
+
+    /// Get the value of a field of an object.
+    ///
+    /// `reference` is where the field is referred to from.
+    fn field(
+        &self,
+        object: &'a Object<'a>,
+        index: usize,
+        reference: &'a [u8],
+    ) -> Result<Value<'a>, Error<'a>> {
+        object.evaluate_field(index, reference, |definitions| {
+            self.eval_definitions(object, definitions)
+        })
+    }
+
+    /// Evaluate the definitions of a field of `object`.
+    ///
+    /// Starting at the last definition, the values are merged for as long as
+    /// they are all objects.
+    fn eval_definitions(
+        &self,
+        object: &'a Object<'a>,
+        definitions: &[Definition<'a>],
+    ) -> Result<Value<'a>, Error<'a>> {
+        let (last, rest) = definitions.split_last().unwrap();
+        let mut scope = last.scope.with_object(object.context(), last.base);
+        for variables in &last.variables {
+            scope = scope.with_variables(variables);
+        }
+        let value = self.eval(last.expression, &scope)?;
+        match value {
+            Value::Object(b) if !rest.is_empty() => match self.eval_definitions(object, rest)? {
+                Value::Object(a) => Ok(Value::Object(self.merge_objects(a, b, true))),
+                _ => Ok(value),
+            },
+            _ => Ok(value),
+        }
+    }
+
+    /// Check if two values are equal.
+    ///
+    /// Lists and objects are compared element by element, evaluating fields
+    /// where necessary. `reference` is used for errors while evaluating fields.
+    fn equal(&self, a: &Value<'a>, b: &Value<'a>, reference: &'a [u8]) -> Result<bool, Error<'a>> {
+        match (a, b) {
+            (Value::Null, Value::Null) => Ok(true),
+            (Value::Bool(a), Value::Bool(b)) => Ok(a == b),
+            (Value::String(a), Value::String(b)) => Ok(a == b),
+            (Value::Duration(a), Value::Duration(b)) => Ok(a == b),
+            (Value::ByteSize(a), Value::ByteSize(b)) => Ok(a == b),
+            (Value::Date(a), Value::Date(b)) => Ok(a == b),
+            (Value::Timestamp(a), Value::Timestamp(b)) => Ok(a == b),
+            (Value::Function(a), Value::Function(b)) => Ok(a == b),
+            (Value::List(a), Value::List(b)) => {
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for (a, b) in a.iter().zip(b.iter()) {
+                    if !self.equal(a, b, reference)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for (index_a, field) in a.fields().iter().enumerate() {
+                    if field.local {
+                        continue;
+                    }
+                    let index_b = match b.index_of(&field.key) {
+                        Some(index_b) => index_b,
+                        None => return Ok(false),
+                    };
+                    let value_a = self.field(a, index_a, reference)?;
+                    let value_b = self.field(b, index_b, reference)?;
+                    if !self.equal(&value_a, &value_b, reference)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            _ => Ok(number_equal(a, b).unwrap_or(false)),
+        }
+    }
+
+    fn lookup(&self, identifier: &'a str, scope: &Scope<'a>) -> Result<Value<'a>, Error<'a>> {
+        let outside_object = || {
+            error(
+                identifier.as_bytes(),
+                format!("`{}' used outside of an object", identifier),
+            )
+        };
+        match identifier {
+            "self" => {
+                let (object, _) = scope.innermost().ok_or_else(outside_object)?;
+                return Ok(Value::Object(object));
+            }
+            "super" => {
+                let (object, base) = scope.innermost().ok_or_else(outside_object)?;
+                let base = base.ok_or_else(|| {
+                    error(
+                        identifier.as_bytes(),
+                        "`super' used in an object that does not extend another object".to_string(),
+                    )
+                })?;
+                return Ok(Value::Object(
+                    self.arena.alloc(Object::in_context(base, object)),
+                ));
+            }
+            "root" => {
+                let object = scope.outermost().ok_or_else(outside_object)?;
+                return Ok(Value::Object(object));
+            }
+            _ => {}
+        }
+        for object in scope.objects() {
+            if let Some(index) = object.binding_index_of(identifier) {
+                return self.field(object, index, identifier.as_bytes());
+            }
+        }
+        if let Some(value) = self.globals.get(identifier) {
+            return Ok(value.clone());
+        }
+        Err(error(
+            identifier.as_bytes(),
+            format!("undefined identifier `{}'", identifier),
+        ))
+    }
+
+    fn dot(
+        &self,
+        lhs: Value<'a>,
+        op_source: &'a str,
+        rhs: &'a Expression<'a>,
+    ) -> Result<Value<'a>, Error<'a>> {
+        let name = match rhs {
+            Expression::Identifier(name) => name,
+            _ => unreachable!("rhs of `.' is always an identifier"),
+        };
+        match lhs {
+            Value::Object(object) => match object.index_of(name) {
+                Some(index) => self.field(object, index, name.as_bytes()),
+                None => Err(error(
+                    name.as_bytes(),
+                    format!("object has no field `{}'", name),
+                )),
+            },
+            Value::Function(ref function) if function.has_fields() => function.field(self, name),
+            other => Err(error(
+                op_source.as_bytes(),
+                format!("cannot access field of {}", other.type_name()),
+            )),
+        }
+    }
+
+    /// Look up `namespace:name`.
+    fn namespace_member(
+        &self,
+        lhs: &'a Expression<'a>,
+        op_source: &'a str,
+        rhs: &'a Expression<'a>,
+    ) -> Result<Value<'a>, Error<'a>> {
+        let (namespace, name) = match (lhs, rhs) {
+            (Expression::Identifier(namespace), Expression::Identifier(name)) => (namespace, name),
+            _ => {
+                return Err(error(
+                    op_source.as_bytes(),
+                    "expected `namespace:name'".to_string(),
+                ))
+            }
+        };
+        let members = self.namespaces.get(*namespace).ok_or_else(|| {
+            error(
+                namespace.as_bytes(),
+                format!("unknown namespace `{}'", namespace),
+            )
+        })?;
+        members.get(*name).cloned().ok_or_else(|| {
+            error(
+                name.as_bytes(),
+                format!("namespace `{}' has no member `{}'", namespace, name),
+            )
+        })
+    }
+
+    /// Evaluate `lhs[index]`, `lhs[start:end]` or `lhs[start, end]`.
+    ///
+    /// A `:` directly inside the brackets makes a slice, rather than a
+    /// namespace access.
+    fn index(
+        &self,
+        lhs: Value<'a>,
+        op_source: &'a str,
+        rhs: &'a Expression<'a>,
+        scope: &Scope<'a>,
+    ) -> Result<Value<'a>, Error<'a>> {
+        let (start, end) = match rhs {
+            Expression::Literal {
+                value: Literal::List(indices),
+                ..
+            } => match &indices[..] {
+                [index] => {
+                    let index = self.eval(index, scope)?;
+                    return self.index_one(lhs, op_source, index);
+                }
+                [start, end] => (Some(start), Some(end)),
+                _ => {
+                    return Err(error(
+                        rhs.location(),
+                        format!("expected 1 or 2 indices, got {}", indices.len()),
+                    ))
+                }
+            },
+            Expression::Literal {
+                value: Literal::Slice(start, end),
+                ..
+            } => (start.as_ref(), end.as_ref()),
+            _ => unreachable!("rhs of `[' is always a list or a slice"),
+        };
+        let bound = |bound: Option<&'a Box<Expression<'a>>>| match bound {
+            Some(bound) => self.eval(bound, scope),
+            None => Ok(Value::Null),
+        };
+        slice(lhs, op_source, &bound(start)?, &bound(end)?)
+    }
+
+    fn index_one(
+        &self,
+        lhs: Value<'a>,
+        op_source: &'a str,
+        index: Value<'a>,
+    ) -> Result<Value<'a>, Error<'a>> {
+        let out_of_range = |i: i64, what: &str, len: usize| {
+            error(
+                op_source.as_bytes(),
+                format!("index {} out of range for {} of length {}", i, what, len),
+            )
+        };
+        match (lhs, index) {
+            (Value::List(mut list), Value::Integer(i)) => match list_position(i, list.len()) {
+                Some(position) => Ok(list.swap_remove(position)),
+                None => Err(out_of_range(i, "list", list.len())),
+            },
+            (Value::String(s), Value::Integer(i)) => {
+                let len = s.chars().count();
+                match list_position(i, len) {
+                    Some(position) => {
+                        Ok(Value::String(s.chars().nth(position).unwrap().to_string()))
+                    }
+                    None => Err(out_of_range(i, "string", len)),
+                }
+            }
+            (Value::Object(object), Value::String(key)) => match object.index_of(&key) {
+                Some(index) => self.field(object, index, op_source.as_bytes()),
+                None => Err(error(
+                    op_source.as_bytes(),
+                    format!("object has no field `{}'", key),
+                )),
+            },
+            (lhs, index) => Err(error(
+                op_source.as_bytes(),
+                format!(
+                    "cannot index {} with {}",
+                    lhs.type_name(),
+                    index.type_name()
+                ),
+            )),
+        }
+    }
+}
+
+/// A field of an object that extends `base`, with `base` recorded for `super`.
+///
+/// Definitions that already refer to another base keep it.
+fn extended_field<'a>(field: &Field<'a>, base: &'a Object<'a>) -> Field<'a> {
+    let mut field = field.clone();
+    for definition in &mut field.definitions {
+        definition.base = definition.base.or(Some(base));
+    }
+    field
+}
+
+/// The position of index `i` in a list of length `len`, counting from the end
+/// if it is negative.
+fn list_position(i: i64, len: usize) -> Option<usize> {
+    let i = if i < 0 { i.checked_add(len as i64)? } else { i };
+    usize::try_from(i).ok().filter(|&i| i < len)
+}
+
+/// Evaluate `lhs[start:end]` on a list or string.
+///
+/// Negative bounds count from the end, and `null` leaves a bound open.
+fn slice<'a>(
+    lhs: Value<'a>,
+    op_source: &'a str,
+    start: &Value<'a>,
+    end: &Value<'a>,
+) -> Result<Value<'a>, Error<'a>> {
+    let len = match &lhs {
+        Value::List(list) => list.len(),
+        Value::String(s) => s.chars().count(),
+        other => {
+            return Err(error(
+                op_source.as_bytes(),
+                format!("cannot slice {}", other.type_name()),
+            ))
+        }
+    };
+    let bound = |value: &Value<'a>, default: usize| match *value {
+        Value::Null => Ok(Some(default)),
+        Value::Integer(i) if i < 0 => Ok(list_position(i, len)),
+        // The end of the slice is one past the last element.
+        Value::Integer(i) => Ok(list_position(i, len + 1)),
+        ref other => Err(error(
+            op_source.as_bytes(),
+            format!(
+                "cannot slice {} with {}",
+                lhs.type_name(),
+                other.type_name()
+            ),
+        )),
+    };
+    // An open bound is written as nothing, like in `xs[1:]`.
+    let show = |value: &Value| match value {
+        Value::Null => String::new(),
+        value => value.to_string(),
+    };
+    let range = match (bound(start, 0)?, bound(end, len)?) {
+        (Some(start), Some(end)) if start <= end => start..end,
+        _ => {
+            return Err(error(
+                op_source.as_bytes(),
+                format!(
+                    "slice {}:{} out of range for {} of length {}",
+                    show(start),
+                    show(end),
+                    lhs.type_name(),
+                    len
+                ),
+            ))
+        }
+    };
+    Ok(match lhs {
+        Value::List(list) => Value::List(list[range].to_vec()),
+        Value::String(s) => Value::String(s.chars().skip(range.start).take(range.len()).collect()),
+        _ => unreachable!(),
+    })
+}
+
+fn boolean<'a>(value: Value<'a>, op_source: &'a str) -> Result<bool, Error<'a>> {
+    match value {
+        Value::Bool(b) => Ok(b),
+        other => Err(error(
+            op_source.as_bytes(),
+            format!(
+                "expected boolean operand for `{}', got {}",
+                op_source,
+                other.type_name()
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parse::Parser;
+
+    fn eval(source: &str) -> Result<String, String> {
+        eval_with(source, DuplicateKeys::Error)
+    }
+
+    fn eval_with(source: &str, duplicate_keys: DuplicateKeys) -> Result<String, String> {
+        let mut parser = Parser::new(source.as_bytes());
+        parser.allow_duplicate_keys = duplicate_keys != DuplicateKeys::Error;
+        let expr = parser.parse_document().map_err(|e| e.message.message)?;
+        let arena = Arena::new();
+        let mut evaluator = Evaluator::new(&arena);
+        evaluator.duplicate_keys = duplicate_keys;
+        let result = evaluator.evaluate(&expr);
+        result.map(|v| v.to_string()).map_err(|e| e.message.message)
+    }
+
+    /// Parse `source` and give it to `evaluate` together with a new
+    /// evaluator, which can be set up first.
+    ///
+    /// Gives the resulting value or the error message as a string.
+    pub(super) fn eval_using<F>(source: &str, evaluate: F) -> Result<String, String>
+    where
+        F: for<'a> FnOnce(&mut Evaluator<'a>, &'a Expression<'a>) -> Result<Value<'a>, Error<'a>>,
+    {
+        let expr = Parser::new(source.as_bytes())
+            .parse_document()
+            .map_err(|e| e.message.message)?;
+        let arena = Arena::new();
+        let mut evaluator = Evaluator::new(&arena);
+        let result = evaluate(&mut evaluator, &expr);
+        result.map(|v| v.to_string()).map_err(|e| e.message.message)
+    }
+
+    #[test]
+    fn object() {
+        assert_eq!(eval("a = 1; b = a + 1"), Ok("{ a = 1, b = 2 }".to_string()));
+        assert_eq!(eval("b = a * 2; a = 3"), Ok("{ b = 6, a = 3 }".to_string()));
+        assert_eq!(
+            eval("a = { b = 1, c = { d = b } }; e = a.c.d"),
+            Ok("{ a = { b = 1, c = { d = 1 } }, e = 1 }".to_string())
+        );
+        assert_eq!(eval("{}"), Ok("{}".to_string()));
+    }
+
+    #[test]
+    fn quoted_keys() {
+        assert_eq!(
+            eval(r#""content-type" = "text/plain"; "example.com" = {}"#),
+            Ok(r#"{ "content-type" = "text/plain", "example.com" = {} }"#.to_string())
+        );
+        assert_eq!(
+            eval(r#"a = { "x y" = 1 }; b = a["x y"]"#),
+            Ok(r#"{ a = { "x y" = 1 }, b = 1 }"#.to_string())
+        );
+    }
+
+    #[test]
+    fn computed_keys() {
+        assert_eq!(
+            eval(r#"prefix = "http"; ports = { [prefix + "_port"] = 80 }"#),
+            Ok(r#"{ prefix = "http", ports = { http_port = 80 } }"#.to_string())
+        );
+        assert_eq!(
+            eval("a = { [1] = 2 }"),
+            Err("object key must be a string, not integer".to_string())
+        );
+        assert_eq!(
+            eval(r#"[name] = 1; name = "a""#),
+            Err("undefined identifier `name'".to_string())
+        );
+    }
+
+    #[test]
+    fn dotted_keys() {
+        assert_eq!(
+            eval("server.http.port = 8080\nserver.http.host = \"x\"\nserver.name = port"),
+            Err("undefined identifier `port'".to_string())
+        );
+        assert_eq!(
+            eval("a.b.c = 1; a.b.d = c + 1; a = { e = 3 }"),
+            Err("duplicate key `a'".to_string())
+        );
+        assert_eq!(
+            eval("a = { e = 3 }; a.b.c = 1; a.b.d = c + 1; a.\"f g\" = e"),
+            Ok(r#"{ a = { e = 3, b = { c = 1, d = 2 }, "f g" = 3 } }"#.to_string())
+        );
+        assert_eq!(
+            eval("{ x = { a.b = 1, a.b = 2 } }"),
+            Err("duplicate key `b'".to_string())
+        );
+        assert_eq!(
+            eval("a.b = 1; a.b.c = 2"),
+            Err("cannot define fields in `b', which is not an object".to_string())
+        );
+        assert_eq!(
+            eval("x = { a.[b] = 1 }"),
+            Err("expected identifier or string literal after `.'".to_string())
+        );
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        assert_eq!(
+            eval(r#"a = 1; "a" = 2"#),
+            Err("duplicate key `a'".to_string())
+        );
+        assert_eq!(
+            eval(r#"x = "a"; y = { a = 1; [x] = 2 }"#),
+            Err("duplicate key `a'".to_string())
+        );
+    }
+
+    /// Where the error from evaluating `source` points at, as the text and its
+    /// offset in the source.
+    fn error_location(source: &str) -> (String, usize) {
+        let expr = Parser::new(source.as_bytes()).parse_document().unwrap();
+        let arena = Arena::new();
+        let e = Evaluator::new(&arena).evaluate(&expr).unwrap_err();
+        let location = e.message.location.unwrap();
+        let offset = location.as_ptr() as usize - source.as_ptr() as usize;
+        (String::from_utf8_lossy(location).into_owned(), offset)
+    }
+
+    #[test]
+    fn duplicate_key_note() {
+        // Keys that are not computed are already checked by the parser.
+        let source = "k = \"a\"\no = { a = 1, [k] = 3 }\n";
+        let expr = Parser::new(source.as_bytes()).parse_document().unwrap();
+        let arena = Arena::new();
+        let e = Evaluator::new(&arena).evaluate(&expr).unwrap_err();
+        assert_eq!(e.message.message, "duplicate key `a'");
+        assert_eq!(e.message.location, Some(&source.as_bytes()[22..23]));
+        assert_eq!(e.notes.len(), 1);
+        assert_eq!(e.notes[0].message, "`a' was first defined here");
+        assert_eq!(e.notes[0].location, Some(&source.as_bytes()[14..15]));
+    }
+
+    #[test]
+    fn duplicate_keys_last_wins() {
+        assert_eq!(
+            eval_with(
+                "a = { x = 1, y = 2 }; b = 1; a = { x = 3 }",
+                DuplicateKeys::LastWins
+            ),
+            Ok("{ a = { x = 3 }, b = 1 }".to_string())
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_merge() {
+        assert_eq!(
+            eval_with(
+                "a = { x = 1, y = { z = 2 } }; a = { y = { w = 3 } }",
+                DuplicateKeys::Merge
+            ),
+            Ok("{ a = { x = 1, y = { z = 2, w = 3 } } }".to_string())
+        );
+        assert_eq!(
+            eval_with("a = { x = 1 }; a = 2", DuplicateKeys::Merge),
+            Ok("{ a = 2 }".to_string())
+        );
+        assert_eq!(
+            eval_with("a = 2; a = { x = 1 }", DuplicateKeys::Merge),
+            Ok("{ a = { x = 1 } }".to_string())
+        );
+        // Fields of the merged object refer to the merged fields.
+        assert_eq!(
+            eval_with(
+                "a = { port = 80, url = port }; a = { port = 81 }",
+                DuplicateKeys::Merge
+            ),
+            Ok("{ a = { port = 81, url = 81 } }".to_string())
+        );
+    }
+
+    #[test]
+    fn locals() {
+        assert_eq!(
+            eval("let base = \"/srv\"; data = base + \"/data\"; logs = base + \"/logs\""),
+            Ok("{ data = \"/srv/data\", logs = \"/srv/logs\" }".to_string())
+        );
+        // Locals are visible in nested objects, and can refer to fields.
+        assert_eq!(
+            eval("let url = host + \":80\"; host = \"a\"; b = { let x = 1; y = { z = url + \"/\" } }"),
+            Ok("{ host = \"a\", b = { y = { z = \"a:80/\" } } }".to_string())
+        );
+        // Locals are not evaluated if they are not used.
+        assert_eq!(
+            eval("let unused = 1 / 0; a = 1"),
+            Ok("{ a = 1 }".to_string())
+        );
+        assert_eq!(
+            eval("a = { let x = 1; y = 2 }; b = a.x"),
+            Err("object has no field `x'".to_string())
+        );
+        assert_eq!(
+            eval("a = { let x = 1; y = 2 } == { y = 2 }"),
+            Ok("{ a = true }".to_string())
+        );
+        assert_eq!(
+            eval("let x = 1; x = 2"),
+            Err("`x' is defined both as a local binding and as a field".to_string())
+        );
+        assert_eq!(
+            eval("let a = { b = 1 }; a.c = 2"),
+            Err("`a' is defined both as a local binding and as a field".to_string())
+        );
+        assert_eq!(
+            eval("a.b = 1; let a.c = 2"),
+            Err("`a' is defined both as a local binding and as a field".to_string())
+        );
+        assert_eq!(
+            eval_with("x = 1; let x = 2", DuplicateKeys::Merge),
+            Err("`x' is defined both as a local binding and as a field".to_string())
+        );
+        // `let' can still be used as a key.
+        assert_eq!(
+            eval("let = 1; let.x = 2; let letter = 3; b = letter"),
+            Err("cannot define fields in `let', which is not an object".to_string())
+        );
+        assert_eq!(
+            eval("let = 1; let letter = 3; b = letter + let"),
+            Ok("{ let = 1, b = 4 }".to_string())
+        );
+    }
+
+    #[test]
+    fn comprehensions() {
+        assert_eq!(
+            eval("hosts = [\"web\", \"db\", \"cache\"]; a = [h + \":80\" for h in hosts if h != \"db\"]"),
+            Ok("{ hosts = [\"web\", \"db\", \"cache\"], a = [\"web:80\", \"cache:80\"] }".to_string())
+        );
+        assert_eq!(
+            eval("[[i, x, y] for i, x in [1, 2] for y in [3, 4] if x + y != 5]"),
+            Ok("[[0, 1, 3], [1, 2, 4]]".to_string())
+        );
+        assert_eq!(
+            eval("let ports = { http = 80, https = 443 }; a = { [name + \"_port\"] = port for name, port in ports }"),
+            Ok("{ a = { http_port = 80, https_port = 443 } }".to_string())
+        );
+        assert_eq!(
+            eval("[k for k in { a = 1, let b = 2, c = 3 }]"),
+            Ok("[\"a\", \"c\"]".to_string())
+        );
+        // Variables take precedence over the fields of the generated object.
+        assert_eq!(
+            eval("{ [v] = v for v in [\"a\", \"v\"] }"),
+            Ok("{ a = \"a\", v = \"v\" }".to_string())
+        );
+        // Values are evaluated lazily.
+        assert_eq!(
+            eval("let a = { [k] = 1 / 0 for k in [\"x\"] }; b = [k for k in a]"),
+            Ok("{ b = [\"x\"] }".to_string())
+        );
+        assert_eq!(
+            eval("{ [k] = 1 for k in [\"x\", \"x\"] }"),
+            Err("duplicate key `x'".to_string())
+        );
+        assert_eq!(
+            eval("[x for x in 1]"),
+            Err("cannot iterate over integer".to_string())
+        );
+        assert_eq!(
+            eval("[x for x in [1] if x]"),
+            Err("expected boolean condition, got integer".to_string())
+        );
+    }
+
+    #[test]
+    fn inheritance() {
+        let base = "let base = { host = \"a\", port = 80, url = host + \":\" + port_name, let port_name = \"http\" }; ";
+        assert_eq!(
+            eval(&(base.to_string() + "x = base { port = 81, let port_name = \"alt\" }")),
+            Ok("{ x = { host = \"a\", port = 81, url = \"a:alt\" } }".to_string())
+        );
+        assert_eq!(
+            eval(&(base.to_string() + "x = base + { host = \"b\", extra = url }")),
+            Ok(
+                "{ x = { host = \"b\", port = 80, url = \"b:http\", extra = \"b:http\" } }"
+                    .to_string()
+            )
+        );
+        // Fields are overridden, not merged.
+        assert_eq!(
+            eval("a = { x = { y = 1 } } { x = { z = 2 } }"),
+            Ok("{ a = { x = { z = 2 } } }".to_string())
+        );
+        // The original object is unchanged.
+        assert_eq!(
+            eval("a = { x = 1, y = x }; b = a { x = 2 } { z = 3 }; c = a.y"),
+            Ok("{ a = { x = 1, y = 1 }, b = { x = 2, y = 2, z = 3 }, c = 1 }".to_string())
+        );
+        assert_eq!(
+            eval("a = 1 { x = 1 }"),
+            Err("cannot extend integer".to_string())
+        );
+        assert_eq!(
+            eval("a = { x = 1 } + 1"),
+            Err("unsupported operand types for `+': object and integer".to_string())
+        );
+    }
+
+    #[test]
+    fn self_super_root() {
+        assert_eq!(
+            eval("host = \"a\"; b = { host = \"b\", url = \"http://\" + self.host, top = root.host }"),
+            Ok("{ host = \"a\", b = { host = \"b\", url = \"http://b\", top = \"a\" } }".to_string())
+        );
+        // Local bindings are not fields, not even of the root.
+        assert_eq!(
+            eval("let defaults = { timeout = 10 }; a = { b = { timeout = root.defaults.timeout * 2 } }"),
+            Err("object has no field `defaults'".to_string())
+        );
+        assert_eq!(
+            eval("defaults = { timeout = 10 }; a = { b = { t = root.defaults.timeout * 2 } }"),
+            Ok("{ defaults = { timeout = 10 }, a = { b = { t = 20 } } }".to_string())
+        );
+        // `super' refers to the extended object, with late binding.
+        assert_eq!(
+            eval("let base = { port = \"80\", url = \"x:\" + port }; a = base { port = \"81\", url = super.url + \"/\" }"),
+            Ok("{ a = { port = \"81\", url = \"x:81/\" } }".to_string())
+        );
+        assert_eq!(
+            eval("let a = { x = 1 }; let b = a { x = super.x + 1 }; c = b { x = super.x * 10 }"),
+            Ok("{ c = { x = 20 } }".to_string())
+        );
+        assert_eq!(
+            eval("self"),
+            Err("`self' used outside of an object".to_string())
+        );
+        assert_eq!(
+            eval("[root]"),
+            Err("`root' used outside of an object".to_string())
+        );
+        assert_eq!(
+            eval("a = super.x"),
+            Err("`super' used in an object that does not extend another object".to_string())
+        );
+        // Comprehension variables are not objects.
+        assert_eq!(
+            eval("a = [self.b + x for x in [1, 2]]; b = 10"),
+            Ok("{ a = [11, 12], b = 10 }".to_string())
+        );
+        assert_eq!(eval("x = self"), Err("object contains itself".to_string()));
+        assert_eq!(
+            eval("a = { b = root }"),
+            Err("object contains itself".to_string())
+        );
+        assert_eq!(
+            eval("x = { y = self }.y"),
+            Err("object contains itself".to_string())
+        );
+        assert_eq!(
+            eval("x = [1, { y = root }]"),
+            Err("object contains itself".to_string())
+        );
+        assert_eq!(error_location("a = { b = root }"), ("b".to_string(), 6));
+    }
+
+    #[test]
+    fn merging() {
+        assert_eq!(
+            eval("a = [1, 2] + [3] + []; b = \"x\" + \"y\"; c = { x = 1, y = 2 } + { y = 3 }"),
+            Ok("{ a = [1, 2, 3], b = \"xy\", c = { x = 1, y = 3 } }".to_string())
+        );
+        let base = "let base = { db = { host = \"a\", port = 5432, opts = { ssl = true } }, tags = [1] }; ";
+        assert_eq!(
+            eval(&(base.to_string() + "x = base & { db = { host = \"b\", opts = { timeout = 3 } } }")),
+            Ok("{ x = { db = { host = \"b\", port = 5432, opts = { ssl = true, timeout = 3 } }, tags = [1] } }".to_string())
+        );
+        // Values that aren't both objects are replaced, not merged.
+        assert_eq!(
+            eval(&(base.to_string() + "x = base & { db = { opts = null }, tags = [2] }")),
+            Ok(
+                "{ x = { db = { host = \"a\", port = 5432, opts = null }, tags = [2] } }"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            eval(&(base.to_string() + "x = base & { db = 1 } & { db = { port = 1 } }")),
+            Ok("{ x = { db = { port = 1 }, tags = [1] } }".to_string())
+        );
+        // Merged fields see the fields of the merged object.
+        assert_eq!(
+            eval("a = { x = { y = 1, z = y } } & { x = { y = 2 } }"),
+            Ok("{ a = { x = { y = 2, z = 2 } } }".to_string())
+        );
+        assert_eq!(eval("a = 6 & 3"), Ok("{ a = 2 }".to_string()));
+        assert_eq!(
+            eval("a = { x = 1 } & [1]"),
+            Err("unsupported operand types for `&': object and list".to_string())
+        );
+        assert_eq!(
+            eval("a = [1] + 1"),
+            Err("unsupported operand types for `+': list and integer".to_string())
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(
+            eval("host = \"a\"; port = 80; url = \"http://${host}:${port}/${port / 2 == 40}\""),
+            Ok("{ host = \"a\", port = 80, url = \"http://a:80/true\" }".to_string())
+        );
+        assert_eq!(
+            eval("[\"${\"${1 + 1}\" + \"}\"}\", \"\\${x}\", \"$x $\", \"${2 ** -1}\"]"),
+            Ok("[\"2}\", \"${x}\", \"$x $\", \"0.5\"]".to_string())
+        );
+        assert_eq!(
+            eval("k = \"http\"; a = { \"${k}_port\" = 1 }"),
+            Ok("{ k = \"http\", a = { http_port = 1 } }".to_string())
+        );
+        assert_eq!(
+            eval("\"x${[1]}\""),
+            Err("cannot interpolate list into a string".to_string())
+        );
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(
+            eval("a = 1_000_000; b = 0xFF_FF; c = 0o7_7; d = 0b1010_1010"),
+            Ok("{ a = 1000000, b = 65535, c = 63, d = 170 }".to_string())
+        );
+        assert_eq!(
+            eval("a = -9223372036854775808; b = 9223372036854775807; c = - 1"),
+            Ok("{ a = -9223372036854775808, b = 9223372036854775807, c = -1 }".to_string())
+        );
+        assert_eq!(
+            eval("9223372036854775808"),
+            Err("constant too large for 64-bit signed integer".to_string())
+        );
+        assert_eq!(
+            eval("-9223372036854775809"),
+            Err("constant too large for 64-bit signed integer".to_string())
+        );
+        assert_eq!(
+            eval("- 9223372036854775808"),
+            Err("constant too large for 64-bit signed integer".to_string())
+        );
+        assert_eq!(
+            eval("-9223372036854775807 - 2"),
+            Err("integer overflow".to_string())
+        );
+        assert_eq!(
+            eval("-(-9223372036854775808)"),
+            Err("integer overflow".to_string())
+        );
+        for source in &["1__000", "1_", "0x_1", "0b1_"] {
+            assert_eq!(eval(source), Err("`_' must be between digits".to_string()));
+        }
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(
+            eval("a = 1.5; b = .25e1; c = 2.; d = 1_0.0_1; e = 0x1.8p1; f = -1e-3"),
+            Ok("{ a = 1.5, b = 2.5, c = 2.0, d = 10.01, e = 3.0, f = -0.001 }".to_string())
+        );
+        assert_eq!(eval("1e"), Err("missing exponent".to_string()));
+        assert_eq!(
+            eval("0x1.8"),
+            Err("hexadecimal floating point literals need a `p' exponent".to_string())
+        );
+        assert_eq!(
+            eval("0b1.1"),
+            Err(
+                "floating point literals must be in decimal or hexadecimal, not in binary"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(
+            eval("a = 30s; b = 1.5m; c = 250ms; d = 2h + 30m; e = 0.5us; f = 1d - 1ns"),
+            Ok(
+                "{ a = 30s, b = 90s, c = 250ms, d = 150m, e = 500ns, f = 86399999999999ns }"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            eval("a = 64KiB; b = 1.5GB; c = 3 * 1MiB; d = 1KB / 3; e = 10B % 4B"),
+            Ok("{ a = 64KiB, b = 1500MB, c = 3MiB, d = 333B, e = 2B }".to_string())
+        );
+        assert_eq!(
+            eval("a = 1m / 20s; b = 1s < 999ms; c = 1KiB == 1024B; d = \"${5s}\""),
+            Ok("{ a = 3.0, b = false, c = true, d = \"5s\" }".to_string())
+        );
+        assert_eq!(eval("1x"), Err("unknown unit `x'".to_string()));
+        assert_eq!(
+            eval("-5s"),
+            Err("a duration cannot be negative".to_string())
+        );
+        assert_eq!(
+            eval("0.5B"),
+            Err("byte size must be a whole number of bytes".to_string())
+        );
+        assert_eq!(eval("20000PB"), Err("byte size too large".to_string()));
+        assert_eq!(eval("1s - 2s"), Err("duration out of range".to_string()));
+        assert_eq!(eval("1s * -1"), Err("duration out of range".to_string()));
+        assert_eq!(eval("1s / 0"), Err("division by zero".to_string()));
+        assert_eq!(
+            eval("1s + 1B"),
+            Err("unsupported operand types for `+': duration and byte size".to_string())
+        );
+        assert_eq!(
+            eval("1 - 1s"),
+            Err("unsupported operand types for `-': integer and duration".to_string())
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            eval("a = 2026-10-18; b = 2026-10-18T12:00:00Z; c = 2026-10-18t14:00:00.250+02:00"),
+            Ok(
+                "{ a = 2026-10-18, b = 2026-10-18T12:00:00Z, c = 2026-10-18T14:00:00.25+02:00 }"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            eval(
+                "a = 2026-10-18 < 2026-10-19; b = 2026-10-18T12:00:00Z == 2026-10-18T13:00:00+01:00; \
+                 c = 2026-10-18T12:00:00-01:00 > 2026-10-18T12:30:00Z; d = \"${2026-01-01}\""
+            ),
+            Ok("{ a = true, b = true, c = true, d = \"2026-01-01\" }".to_string())
+        );
+        assert_eq!(
+            eval("2026-10-18 - 1"),
+            Err("unsupported operand types for `-': date and integer".to_string())
+        );
+        assert_eq!(
+            eval("-2026-10-18"),
+            Err("unsupported operand type for unary `-': date".to_string())
+        );
+        assert_eq!(
+            eval("2026-10-18 < 2026-10-18T12:00:00Z"),
+            Err("unsupported operand types for `<': date and timestamp".to_string())
+        );
+        assert_eq!(eval("2026-02-29"), Err("invalid date".to_string()));
+        assert_eq!(
+            eval("2026-10-18T24:00:00Z"),
+            Err("invalid time".to_string())
+        );
+        assert_eq!(
+            eval("2026-10-18T12:00Z"),
+            Err("expected time (hh:mm:ss) after `T'".to_string())
+        );
+        assert_eq!(
+            eval("2026-10-18T12:00:00"),
+            Err("expected `Z' or a time zone offset (+hh:mm or -hh:mm)".to_string())
+        );
+        assert_eq!(
+            eval("2026-10-18T12:00:00+24:00"),
+            Err("invalid time zone offset".to_string())
+        );
+        // With spaces, it's still a subtraction.
+        assert_eq!(eval("2026 - 10 - 18"), Ok("1998".to_string()));
+    }
+
+    #[test]
+    fn namespaces() {
+        let eval = |source: &str| {
+            let expr = Parser::new(source.as_bytes()).parse_document().unwrap();
+            let arena = Arena::new();
+            let mut evaluator = Evaluator::new(&arena);
+            evaluator.define("math", "pi", Value::Double(3.5));
+            evaluator.define("math", "e", Value::Integer(2));
+            let result = evaluator.evaluate(&expr);
+            result.map(|v| v.to_string()).map_err(|e| e.message.message)
+        };
+        // Namespaces are separate from keys.
+        assert_eq!(
+            eval("math = 1; pi = math:pi; e = math:e * math"),
+            Ok("{ math = 1, pi = 3.5, e = 2 }".to_string())
+        );
+        assert_eq!(eval("x:pi"), Err("unknown namespace `x'".to_string()));
+        assert_eq!(
+            eval("math:tau"),
+            Err("namespace `math' has no member `tau'".to_string())
+        );
+        assert_eq!(
+            eval("a = { b = 1 }; c = a.b:pi"),
+            Err("expected `namespace:name'".to_string())
+        );
+    }
+
+    #[test]
+    fn indexing() {
+        assert_eq!(
+            eval("let xs = [1, 2, 3, 4]; a = xs[0]; b = xs[-1]; c = \"héllo\"[1]; d = { x = 1 }[\"x\"]"),
+            Ok("{ a = 1, b = 4, c = \"é\", d = 1 }".to_string())
+        );
+        assert_eq!(
+            eval("let xs = [1, 2, 3, 4]; let i = 1; a = xs[1:3]; b = xs[i, -1]; c = xs[-2, null]; d = xs[null, 0]"),
+            Ok("{ a = [2, 3], b = [2, 3], c = [3, 4], d = [] }".to_string())
+        );
+        assert_eq!(
+            eval("a = \"héllo\"[1:3]; b = \"hello\"[-3, null]"),
+            Ok("{ a = \"él\", b = \"llo\" }".to_string())
+        );
+        // The `:' of a slice has the lowest precedence, and either bound can
+        // be left out.
+        assert_eq!(
+            eval("let xs = [1, 2, 3, 4]; let n = 4; let i = 1; a = xs[1:n-1]; b = xs[i+1:3]; c = xs[1:]; d = xs[:-1]; e = xs[:]; f = xs[i : i * 2]"),
+            Ok("{ a = [2, 3], b = [3], c = [2, 3, 4], d = [1, 2, 3], e = [1, 2, 3, 4], f = [2] }".to_string())
+        );
+        // A `:' directly between two identifiers is namespace access.
+        assert_eq!(
+            eval("let xs = [1, 2, 3]; a = xs[std:len(xs) - 1]; b = xs[std:len([1]):]"),
+            Ok("{ a = 3, b = [2, 3] }".to_string())
+        );
+        assert_eq!(
+            eval("[1, 2][3:]"),
+            Err("slice 3: out of range for list of length 2".to_string())
+        );
+        assert_eq!(
+            eval("[1, 2][2]"),
+            Err("index 2 out of range for list of length 2".to_string())
+        );
+        assert_eq!(
+            eval("[1, 2][-3]"),
+            Err("index -3 out of range for list of length 2".to_string())
+        );
+        assert_eq!(
+            eval("\"ab\"[5]"),
+            Err("index 5 out of range for string of length 2".to_string())
+        );
+        assert_eq!(
+            eval("[1, 2][1:3]"),
+            Err("slice 1:3 out of range for list of length 2".to_string())
+        );
+        assert_eq!(
+            eval("[1, 2][2:1]"),
+            Err("slice 2:1 out of range for list of length 2".to_string())
+        );
+        assert_eq!(
+            eval("[1, 2][1, 2, 3]"),
+            Err("expected 1 or 2 indices, got 3".to_string())
+        );
+        assert_eq!(
+            eval("{ a = 1 }[0:1]"),
+            Err("cannot slice object".to_string())
+        );
+        assert_eq!(
+            eval("[1][\"a\"]"),
+            Err("cannot index list with string".to_string())
+        );
+        assert_eq!(error_location("a = [1][5]"), ("[".to_string(), 7));
+        assert_eq!(error_location("a = \"x\"[0:2]"), ("[".to_string(), 7));
+    }
+
+    #[test]
+    fn builtins() {
+        assert_eq!(
+            eval("let o = { a = 1, b = o.a + 1 }; a = std:len([1, 2]); b = std:len(\"héllo\"); c = std:len(o); d = std:keys(o); e = std:values(o)"),
+            Ok("{ a = 2, b = 5, c = 2, d = [\"a\", \"b\"], e = [1, 2] }".to_string())
+        );
+        assert_eq!(
+            eval("a = std:map([\"x\", \"y\"], std:upper); b = std:filter([\"a\", \"\"], std:len); c = std:sort([3, 1.5, 2])"),
+            Err("`std:filter' expects `std:len' to give a boolean, got integer".to_string())
+        );
+        assert_eq!(
+            eval("a = std:map([\"x\", \"y\"], std:upper); b = std:sort([3, 1.5, 2]); c = std:sort([\"b\", \"a\"])"),
+            Ok("{ a = [\"X\", \"Y\"], b = [1.5, 2, 3], c = [\"a\", \"b\"] }".to_string())
+        );
+        assert_eq!(
+            eval("a = std:join([\"a\", \"b\"], \", \"); b = std:split(\"a,b,,c\", \",\"); c = std:replace(\"aXbX\", \"X\", \"-\"); d = std:lower(\"AbC\")"),
+            Ok("{ a = \"a, b\", b = [\"a\", \"b\", \"\", \"c\"], c = \"a-b-\", d = \"abc\" }".to_string())
+        );
+        assert_eq!(
+            eval("a = std:min(3, 1, 2); b = std:max([1s, 2m, 3ms]); c = std:range(3); d = std:range(5, 0, -2)"),
+            Ok("{ a = 1, b = 2m, c = [0, 1, 2], d = [5, 3, 1] }".to_string())
+        );
+        assert_eq!(
+            eval("a = std:contains([1, [2]], [2]); b = std:contains(\"hello\", \"ell\"); c = std:contains({ x = 1 }, \"y\")"),
+            Ok("{ a = true, b = true, c = false }".to_string())
+        );
+        assert_eq!(
+            eval("a = std:format(\"{} = {} {{}}\", \"x\", [1, \"y\"]); b = std:to_string({ c = 1 }); c = std:to_int(\" 42 \"); d = std:to_int(-2.9)"),
+            Ok("{ a = \"x = [1, \\\"y\\\"] {}\", b = \"{ c = 1 }\", c = 42, d = -2 }".to_string())
+        );
+        assert_eq!(
+            eval("std:len(1, 2)"),
+            Err("`std:len' takes 1 argument, got 2".to_string())
+        );
+        assert_eq!(
+            eval("std:range()"),
+            Err("`std:range' takes 1 to 3 arguments, got 0".to_string())
+        );
+        assert_eq!(
+            eval("std:max()"),
+            Err("`std:max' takes at least 1 argument, got 0".to_string())
+        );
+        assert_eq!(
+            eval("std:upper(1)"),
+            Err("`std:upper' expects a string as argument 1, got integer".to_string())
+        );
+        assert_eq!(
+            eval("std:min([])"),
+            Err("`std:min' of an empty list".to_string())
+        );
+        assert_eq!(
+            eval("std:sort([1, \"a\"])"),
+            Err("`std:sort' cannot compare string and integer".to_string())
+        );
+        assert_eq!(
+            eval("std:format(\"{}\")"),
+            Err("`std:format' got more `{}' than the 0 values given".to_string())
+        );
+        assert_eq!(
+            eval("std:to_int(\"x\")"),
+            Err("`std:to_int' cannot convert \"x\" to an integer".to_string())
+        );
+        assert_eq!(eval("std:len"), Ok("std:len".to_string()));
+        assert_eq!(
+            eval("x = std:len; a = std:len == std:len; b = x == x; c = std:len == std:keys; d = std:contains([std:len], std:len)"),
+            Ok("{ x = std:len, a = true, b = true, c = false, d = true }".to_string())
+        );
+        assert_eq!(
+            eval("std:range(100000000000)"),
+            Err(
+                "`std:range' would give 100000000000 elements, more than the maximum of 1000000"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            eval("a = std:len(std:range(0, 1000000)); b = std:range(-9223372036854775808, 9223372036854775807, 9223372036854775807)"),
+            Ok("{ a = 1000000, b = [-9223372036854775808, -1, 9223372036854775806] }".to_string())
+        );
+        assert_eq!(
+            eval("1(2)"),
+            Err("cannot call a value of type integer".to_string())
+        );
+        assert_eq!(error_location("a = std:len(1)"), ("(".to_string(), 11));
+        assert_eq!(
+            eval("x = std:to_string(self)"),
+            Err("recursive definition of `x'".to_string())
+        );
+        assert_eq!(
+            eval("a = 1; x = { y = [std:format(\"{}\", root)] }"),
+            Err("recursive definition of `y'".to_string())
+        );
+        assert_eq!(
+            error_location("x = std:to_string(self)"),
+            ("(".to_string(), 17)
+        );
+        assert_eq!(
+            eval("x = { y = 1 }; z = std:to_string(x)"),
+            Ok("{ x = { y = 1 }, z = \"{ y = 1 }\" }".to_string())
+        );
+    }
+
+    #[test]
+    fn booleans() {
+        assert_eq!(
+            eval("a = true; b = !a; c = null; d = a && !b || false"),
+            Ok("{ a = true, b = false, c = null, d = true }".to_string())
+        );
+        assert_eq!(
+            eval("a = false && undefined; b = true || undefined"),
+            Ok("{ a = false, b = true }".to_string())
+        );
+        assert_eq!(
+            eval("1 && true"),
+            Err("expected boolean operand for `&&', got integer".to_string())
+        );
+        assert_eq!(
+            eval("!1"),
+            Err("unsupported operand type for unary `!': integer".to_string())
+        );
+        // Keywords can still be used as keys.
+        assert_eq!(
+            eval("null = 1; true = false"),
+            Ok("{ null = 1, true = false }".to_string())
+        );
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_eq!(
+            eval("a = if 1 < 2 then \"yes\" else \"no\"; b = if a == \"no\" then 1 else 2 + 3"),
+            Ok("{ a = \"yes\", b = 5 }".to_string())
+        );
+        // Only the branch that is taken is evaluated.
+        assert_eq!(
+            eval("a = if true then 1 else undefined; b = if false then undefined else 2"),
+            Ok("{ a = 1, b = 2 }".to_string())
+        );
+        assert_eq!(
+            eval("x = 3; a = if x == 1 then \"one\" else if x == 2 then \"two\" else \"many\""),
+            Ok("{ x = 3, a = \"many\" }".to_string())
+        );
+        assert_eq!(
+            eval("a = [if true then 1 else 2, 3]; b = 1 + if false then 1 else 2 * 3"),
+            Ok("{ a = [1, 3], b = 7 }".to_string())
+        );
+        assert_eq!(
+            eval("a = if 1 then 2 else 3"),
+            Err("expected boolean condition, got integer".to_string())
+        );
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(
+            eval("[1 < 2, 2 <= 2 ** -1, 3 > 2 ** -1, \"b\" >= \"a\", 1 == 2 * 2 ** -1, null != null]"),
+            Ok("[true, false, true, true, true, false]".to_string())
+        );
+        assert_eq!(
+            eval("[[1, {a = 2}] == [1, {a = 2}], {a = 1, b = 2} == {b = 2, a = 1}, 1 == \"1\"]"),
+            Ok("[true, true, false]".to_string())
+        );
+        assert_eq!(
+            eval("[9007199254740993 == 9007199254740992, 9007199254740993 > 9007199254740992]"),
+            Ok("[false, true]".to_string())
+        );
+        assert_eq!(
+            eval("1 < \"2\""),
+            Err("unsupported operand types for `<': integer and string".to_string())
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(eval("a = b"), Err("undefined identifier `b'".to_string()));
+        assert_eq!(
+            eval("a = b; b = a"),
+            Err("recursive definition of `a'".to_string())
+        );
+        assert_eq!(
+            eval("x = { y = x }"),
+            Err("object contains itself".to_string())
+        );
+        assert_eq!(error_location("x = { y = x }"), ("y".to_string(), 6));
+        assert_eq!(
+            eval("9223372036854775807 + 1"),
+            Err("integer overflow".to_string())
+        );
+        assert_eq!(eval("1 / 0"), Err("division by zero".to_string()));
+        assert_eq!(
+            eval(r#"1 + "a""#),
+            Err("unsupported operand types for `+': integer and string".to_string())
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Calling Rust functions from expressions.
+
+use std::fmt;
+use std::time::Duration;
+
+use super::function::{Call, Function};
+use super::value::Value;
+use datetime::{Date, Timestamp};
+use error::Error;
+
+/// A type that arguments of native functions can be converted to.
+pub trait FromValue: Sized {
+    /// What is expected, for in error messages, such as `a string`.
+    fn expected() -> String;
+
+    /// Convert the value, if it is of the right type.
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+/// A type that native functions can return.
+pub trait IntoValue {
+    fn into_value<'a>(self) -> Value<'a>;
+}
+
+/// The result of a native function: a value, or a `Result` of which the
+/// error is reported at the call.
+pub trait NativeResult {
+    fn into_result<'a>(self) -> Result<Value<'a>, String>;
+}
+
+/// A Rust function or closure that can be registered with
+/// `Evaluator::register`.
+///
+/// This is implemented for functions taking up to four arguments that
+/// implement `FromValue`, returning a `NativeResult`.
+pub trait NativeFunction<Arguments> {
+    fn into_function(self, name: &str) -> Function;
+}
+
+fn argument<'a, T: FromValue>(
+    call: &Call<'_, 'a>,
+    index: usize,
+    value: Value<'a>,
+) -> Result<T, Error<'a>> {
+    match T::from_value(value.clone()) {
+        Some(argument) => Ok(argument),
+        None => Err(call.type_error(index, &T::expected(), &value)),
+    }
+}
+
+macro_rules! native_function {
+    ($n:expr; $($argument:ident),*) => {
+        impl<F, R, $($argument),*> NativeFunction<($($argument,)*)> for F
+        where
+            F: Fn($($argument),*) -> R + 'static,
+            R: NativeResult,
+            $($argument: FromValue,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_function(self, name: &str) -> Function {
+                Function::new(name, $n, Some($n), move |call, arguments| {
+                    let mut arguments = arguments.into_iter().enumerate();
+                    $(
+                        let (index, value) = arguments.next().unwrap();
+                        let $argument = argument::<$argument>(call, index, value)?;
+                    )*
+                    self($($argument),*)
+                        .into_result()
+                        .map_err(|message| call.error(format!("`{}': {}", call.name, message)))
+                })
+            }
+        }
+    };
+}
+
+native_function!(0;);
+native_function!(1; A);
+native_function!(2; A, B);
+native_function!(3; A, B, C);
+native_function!(4; A, B, C, D);
+
+impl<T: IntoValue> NativeResult for T {
+    fn into_result<'a>(self) -> Result<Value<'a>, String> {
+        Ok(self.into_value())
+    }
+}
+
+impl<T: IntoValue, E: fmt::Display> NativeResult for Result<T, E> {
+    fn into_result<'a>(self) -> Result<Value<'a>, String> {
+        self.map(IntoValue::into_value).map_err(|e| e.to_string())
+    }
+}
+
+macro_rules! convert {
+    ($type:ty, $expected:expr, $variant:ident) => {
+        impl FromValue for $type {
+            fn expected() -> String {
+                $expected.to_string()
+            }
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+        }
+
+        impl IntoValue for $type {
+            fn into_value<'a>(self) -> Value<'a> {
+                Value::$variant(self)
+            }
+        }
+    };
+}
+
+convert!(bool, "a boolean", Bool);
+convert!(i64, "an integer", Integer);
+convert!(String, "a string", String);
+convert!(Duration, "a duration", Duration);
+convert!(Date, "a date", Date);
+convert!(Timestamp, "a timestamp", Timestamp);
+
+/// Integers are accepted where floating point numbers are expected.
+impl FromValue for f64 {
+    fn expected() -> String {
+        "a number".to_string()
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Integer(i) => Some(i as f64),
+            Value::Double(d) => Some(d),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn expected() -> String {
+        format!("a list where each element is {}", T::expected())
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::List(list) => list.into_iter().map(T::from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// `null` is converted to `None`.
+impl<T: FromValue> FromValue for Option<T> {
+    fn expected() -> String {
+        format!("{} or null", T::expected())
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl IntoValue for () {
+    fn into_value<'a>(self) -> Value<'a> {
+        Value::Null
+    }
+}
+
+impl IntoValue for i32 {
+    fn into_value<'a>(self) -> Value<'a> {
+        Value::Integer(i64::from(self))
+    }
+}
+
+impl IntoValue for u32 {
+    fn into_value<'a>(self) -> Value<'a> {
+        Value::Integer(i64::from(self))
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value<'a>(self) -> Value<'a> {
+        Value::Double(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value<'a>(self) -> Value<'a> {
+        Value::String(self.to_string())
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value<'a>(self) -> Value<'a> {
+        Value::List(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+/// `None` is converted to `null`.
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value<'a>(self) -> Value<'a> {
+        self.map_or(Value::Null, IntoValue::into_value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eval::test::eval_using;
+
+    fn eval(source: &str) -> Result<String, String> {
+        eval_using(source, |evaluator, expr| {
+            evaluator.register("cpu_count", || 8);
+            evaluator.register("secret", |name: String| match name.as_str() {
+                "db/password" => Ok("hunter2"),
+                _ => Err(format!("no secret named `{}'", name)),
+            });
+            evaluator.register(
+                "net:lookup_zone",
+                |region: String, fallback: Option<i64>| match region.as_str() {
+                    "eu" => Some(vec![1, 2]),
+                    _ => fallback.map(|zone| vec![zone as i32]),
+                },
+            );
+            evaluator.register("math:scale", |x: f64, factors: Vec<f64>| {
+                factors.iter().fold(x, |x, f| x * f)
+            });
+            evaluator.evaluate(expr)
+        })
+    }
+
+    #[test]
+    fn calls() {
+        assert_eq!(
+            eval("a = cpu_count(); b = secret(\"db/password\"); c = net:lookup_zone(\"eu\", null)"),
+            Ok("{ a = 8, b = \"hunter2\", c = [1, 2] }".to_string())
+        );
+        assert_eq!(
+            eval("a = net:lookup_zone(\"us\", 3); b = net:lookup_zone(\"us\", null); c = math:scale(2, [1.5, 2])"),
+            Ok("{ a = [3], b = null, c = 6.0 }".to_string())
+        );
+        // Fields take precedence over registered functions.
+        assert_eq!(
+            eval("cpu_count = 2; a = cpu_count"),
+            Ok("{ cpu_count = 2, a = 2 }".to_string())
+        );
+        assert_eq!(eval("cpu_count"), Ok("cpu_count".to_string()));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            eval("secret(\"x\")"),
+            Err("`secret': no secret named `x'".to_string())
+        );
+        assert_eq!(
+            eval("secret(1)"),
+            Err("`secret' expects a string as argument 1, got integer".to_string())
+        );
+        assert_eq!(
+            eval("net:lookup_zone(\"eu\", 1.5)"),
+            Err(
+                "`net:lookup_zone' expects an integer or null as argument 2, got float".to_string()
+            )
+        );
+        assert_eq!(
+            eval("math:scale(1, [1, \"2\"])"),
+            Err("`math:scale' expects a list where each element is a number as argument 2, got list".to_string())
+        );
+        assert_eq!(
+            eval("cpu_count(1)"),
+            Err("`cpu_count' takes 0 arguments, got 1".to_string())
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use std::convert::TryFrom;
+use std::time;
+
+use super::value::Value;
+use error::{error, Error};
+use operator::{BinaryOperator, UnaryOperator};
+use unit::Quantity;
+
+pub fn unary_op<'a>(
+    op: UnaryOperator,
+    op_source: &'a str,
+    rhs: Value<'a>,
+) -> Result<Value<'a>, Error<'a>> {
+    use self::UnaryOperator::*;
+    use self::Value::*;
+    match (op, rhs) {
+        (Plus, Integer(i)) => Ok(Integer(i)),
+        (Plus, Double(d)) => Ok(Double(d)),
+        (Plus, Duration(d)) => Ok(Duration(d)),
+        (Plus, ByteSize(b)) => Ok(ByteSize(b)),
+        (Minus, Integer(i)) => i
+            .checked_neg()
+            .map(Integer)
+            .ok_or_else(|| overflow(op_source)),
+        (Minus, Double(d)) => Ok(Double(-d)),
+        (Complement, Integer(i)) => Ok(Integer(!i)),
+        (LogicalNot, Bool(b)) => Ok(Bool(!b)),
+        (_, rhs) => Err(error(
+            op_source.as_bytes(),
+            format!(
+                "unsupported operand type for unary `{}': {}",
+                op_source,
+                rhs.type_name()
+            ),
+        )),
+    }
+}
+
+/// Apply a binary operator to two values.
+///
+/// `Dot`, `Index`, `Call`, `Extend`, `Equal`, `Inequal`, `LogicalAnd` and
+/// `LogicalOr` are not handled here, as they need more than just the values of
+/// their operands. Neither are `Plus` and `BitAnd` on two objects.
+pub fn binary_op<'a>(
+    op: BinaryOperator,
+    op_source: &'a str,
+    lhs: Value<'a>,
+    rhs: Value<'a>,
+) -> Result<Value<'a>, Error<'a>> {
+    use self::BinaryOperator::*;
+    use self::Value::*;
+    let overflow = || overflow(op_source);
+    match (op, lhs, rhs) {
+        (Plus, Integer(a), Integer(b)) => a.checked_add(b).map(Integer).ok_or_else(overflow),
+        (Minus, Integer(a), Integer(b)) => a.checked_sub(b).map(Integer).ok_or_else(overflow),
+        (Times, Integer(a), Integer(b)) => a.checked_mul(b).map(Integer).ok_or_else(overflow),
+        (Divide, Integer(_), Integer(0)) | (Modulo, Integer(_), Integer(0)) => {
+            Err(error(op_source.as_bytes(), "division by zero".to_string()))
+        }
+        (Divide, Integer(a), Integer(b)) => a.checked_div(b).map(Integer).ok_or_else(overflow),
+        (Modulo, Integer(a), Integer(b)) => a.checked_rem(b).map(Integer).ok_or_else(overflow),
+        (Power, Integer(a), Integer(b)) if b < 0 => Ok(Double((a as f64).powf(b as f64))),
+        (Power, Integer(a), Integer(b)) => {
+            if b > i64::from(u32::MAX) {
+                match a {
+                    0 | 1 => Ok(Integer(a)),
+                    -1 => Ok(Integer(if b % 2 == 0 { 1 } else { -1 })),
+                    _ => Err(overflow()),
+                }
+            } else {
+                a.checked_pow(b as u32).map(Integer).ok_or_else(overflow)
+            }
+        }
+        (LeftShift, Integer(_), Integer(b)) | (RightShift, Integer(_), Integer(b))
+            if !(0..64).contains(&b) =>
+        {
+            Err(error(
+                op_source.as_bytes(),
+                format!("shift amount {} is out of range", b),
+            ))
+        }
+        (LeftShift, Integer(a), Integer(b)) => Ok(Integer(a << b)),
+        (RightShift, Integer(a), Integer(b)) => Ok(Integer(a >> b)),
+        (BitAnd, Integer(a), Integer(b)) => Ok(Integer(a & b)),
+        (BitOr, Integer(a), Integer(b)) => Ok(Integer(a | b)),
+        (BitXor, Integer(a), Integer(b)) => Ok(Integer(a ^ b)),
+        (Plus, String(a), String(b)) => Ok(String(a + &b)),
+        (Plus, List(mut a), List(b)) => {
+            a.extend(b);
+            Ok(List(a))
+        }
+        (op, Integer(a), Integer(b)) if is_comparison(op) => Ok(Bool(compare(op, &a, &b))),
+        (op, String(a), String(b)) if is_comparison(op) => Ok(Bool(compare(op, &a, &b))),
+        (op, Date(a), Date(b)) if is_comparison(op) => Ok(Bool(compare(op, &a, &b))),
+        (op, Timestamp(a), Timestamp(b)) if is_comparison(op) => Ok(Bool(compare(op, &a, &b))),
+        (op, lhs, rhs) if as_amount(&lhs).is_some() || as_amount(&rhs).is_some() => {
+            amount_op(op, op_source, &lhs, &rhs)
+                .unwrap_or_else(|| Err(unsupported(op_source, &lhs, &rhs)))
+        }
+        (op, lhs, rhs) => match (as_double(&lhs), as_double(&rhs)) {
+            (Some(a), Some(b)) if is_arithmetic(op) => Ok(Double(match op {
+                Plus => a + b,
+                Minus => a - b,
+                Times => a * b,
+                Divide => a / b,
+                Modulo => a % b,
+                _ => a.powf(b),
+            })),
+            (Some(a), Some(b)) if is_comparison(op) => Ok(Bool(compare(op, &a, &b))),
+            _ => Err(unsupported(op_source, &lhs, &rhs)),
+        },
+    }
+}
+
+fn unsupported<'a>(op_source: &'a str, lhs: &Value, rhs: &Value) -> Error<'a> {
+    error(
+        op_source.as_bytes(),
+        format!(
+            "unsupported operand types for `{}': {} and {}",
+            op_source,
+            lhs.type_name(),
+            rhs.type_name()
+        ),
+    )
+}
+
+/// Apply a binary operator to a duration or byte size.
+///
+/// These can be added to, subtracted from, divided by or compared with another
+/// value of the same kind, and multiplied or divided by an integer.
+///
+/// Gives `None` if the operator doesn't apply to these operands.
+fn amount_op<'a>(
+    op: BinaryOperator,
+    op_source: &'a str,
+    lhs: &Value,
+    rhs: &Value,
+) -> Option<Result<Value<'a>, Error<'a>>> {
+    use self::BinaryOperator::*;
+    let division_by_zero = || {
+        Some(Err(error(
+            op_source.as_bytes(),
+            "division by zero".to_string(),
+        )))
+    };
+    let (quantity, result) = match (as_amount(lhs), as_amount(rhs), lhs, rhs) {
+        (Some((q, a)), Some((r, b)), _, _) if q == r => match op {
+            Plus => (q, a.checked_add(b)),
+            Minus => (q, a.checked_sub(b)),
+            Divide | Modulo if b == 0 => return division_by_zero(),
+            Divide => return Some(Ok(Value::Double(a as f64 / b as f64))),
+            Modulo => (q, Some(a % b)),
+            _ if is_comparison(op) => return Some(Ok(Value::Bool(compare(op, &a, &b)))),
+            _ => return None,
+        },
+        (Some((q, a)), None, _, &Value::Integer(b))
+        | (None, Some((q, a)), &Value::Integer(b), _)
+            if op == Times =>
+        {
+            (q, u128::try_from(b).ok().and_then(|b| a.checked_mul(b)))
+        }
+        (Some((q, a)), None, _, &Value::Integer(b)) if op == Divide => {
+            if b == 0 {
+                return division_by_zero();
+            }
+            (q, u128::try_from(b).ok().map(|b| a / b))
+        }
+        _ => return None,
+    };
+    Some(
+        result
+            .and_then(|amount| from_amount(quantity, amount))
+            .ok_or_else(|| {
+                error(
+                    op_source.as_bytes(),
+                    format!("{} out of range", quantity.name()),
+                )
+            }),
+    )
+}
+
+/// The amount of nanoseconds or bytes in a duration or byte size.
+fn as_amount(value: &Value) -> Option<(Quantity, u128)> {
+    match *value {
+        Value::Duration(d) => Some((Quantity::Duration, d.as_nanos())),
+        Value::ByteSize(b) => Some((Quantity::ByteSize, u128::from(b))),
+        _ => None,
+    }
+}
+
+fn from_amount<'a>(quantity: Quantity, amount: u128) -> Option<Value<'a>> {
+    let amount = u64::try_from(amount).ok()?;
+    Some(match quantity {
+        Quantity::Duration => Value::Duration(time::Duration::from_nanos(amount)),
+        Quantity::ByteSize => Value::ByteSize(amount),
+    })
+}
+
+fn is_arithmetic(op: BinaryOperator) -> bool {
+    use self::BinaryOperator::*;
+    matches!(op, Plus | Minus | Times | Divide | Modulo | Power)
+}
+
+fn is_comparison(op: BinaryOperator) -> bool {
+    use self::BinaryOperator::*;
+    matches!(op, Less | Greater | LessOrEqual | GreaterOrEqual)
+}
+
+fn compare<T: PartialOrd>(op: BinaryOperator, a: &T, b: &T) -> bool {
+    use self::BinaryOperator::*;
+    match op {
+        Less => a < b,
+        Greater => a > b,
+        LessOrEqual => a <= b,
+        _ => a >= b,
+    }
+}
+
+/// Check if two numbers are equal.
+///
+/// Compares two integers exactly, instead of through `f64`.
+pub fn number_equal(a: &Value, b: &Value) -> Option<bool> {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a == b),
+        _ => match (as_double(a), as_double(b)) {
+            (Some(a), Some(b)) => Some(a == b),
+            _ => None,
+        },
+    }
+}
+
+fn as_double(value: &Value) -> Option<f64> {
+    match *value {
+        Value::Integer(i) => Some(i as f64),
+        Value::Double(d) => Some(d),
+        _ => None,
+    }
+}
+
+fn overflow(op_source: &str) -> Error<'_> {
+    error(op_source.as_bytes(), "integer overflow".to_string())
+}
@@ -0,0 +1,138 @@
+//! Overriding values in a document, like `--set server.port=9090` on the
+//! command line.
+
+use super::import::contains;
+use super::scope::Scope;
+use super::value::Value;
+use super::Evaluator;
+use error::{error, Error, Message};
+use expression::{Expression, Literal};
+
+/// Say which override an error is about.
+fn invalid_override<'a>(source: &[u8], mut e: Error<'a>) -> Error<'a> {
+    e.message.message = format!(
+        "invalid override `{}': {}",
+        String::from_utf8_lossy(source),
+        e.message.message
+    );
+    e
+}
+
+impl<'a> Evaluator<'a> {
+    /// Apply overrides like `server.port=9090` to an evaluated document.
+    ///
+    /// The part after the `=` is an expression, which is evaluated in the
+    /// document, just like the fields of the document itself. Objects are
+    /// merged, such that `server.port=9090` only changes `port`. Fields that
+    /// depend on an overridden value see the new value.
+    pub fn apply_overrides<S: AsRef<str>>(
+        &self,
+        document: Value<'a>,
+        overrides: &[S],
+    ) -> Result<Value<'a>, Error<'a>> {
+        let mut document = match document {
+            Value::Object(object) => object,
+            other => {
+                return Err(Error {
+                    message: Message {
+                        message: format!("cannot override fields of {}", other.type_name()),
+                        location: None,
+                    },
+                    notes: vec![],
+                })
+            }
+        };
+        let mut sources = Vec::new();
+        for source in overrides {
+            let source = self.arena.alloc_source(source.as_ref().as_bytes().to_vec());
+            sources.push(source);
+            let expr = self
+                .parser(source)
+                .parse_document()
+                .map_err(|e| invalid_override(source, e))?;
+            match &expr {
+                Expression::Literal {
+                    value: Literal::Object(_, _, locals),
+                    ..
+                } if locals[..] == [false] => {}
+                _ => {
+                    let e = error(source, "expected `path=expression'".to_string());
+                    return Err(invalid_override(source, e));
+                }
+            }
+            let expr = self.arena.alloc_expression(expr);
+            match self.eval(expr, &Scope::new()) {
+                Ok(Value::Object(object)) => {
+                    document = self.merge_objects(document, object, true);
+                }
+                Ok(_) => unreachable!(),
+                Err(e) => return Err(invalid_override(source, e)),
+            }
+        }
+        let document = Value::Object(document);
+        // The reference is only used if the document is already being forced,
+        // which it isn't.
+        self.force(&document, &[]).map_err(|e| {
+            let location = e.message.location;
+            match sources
+                .iter()
+                .find(|source| location.map_or(false, |l| contains(source, l)))
+            {
+                Some(source) => invalid_override(source, e),
+                None => e,
+            }
+        })?;
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use eval::test::eval_using;
+
+    fn eval(source: &str, overrides: &[&str]) -> Result<String, String> {
+        eval_using(source, |evaluator, expr| {
+            let document = evaluator.evaluate(expr)?;
+            evaluator.apply_overrides(document, overrides)
+        })
+    }
+
+    const DOCUMENT: &str =
+        "server = { host = \"x\", port = 8080 }; url = \"${server.host}:${server.port}\"; features = [\"a\"]";
+
+    #[test]
+    fn overrides() {
+        assert_eq!(
+            eval(DOCUMENT, &["server.port=9090", "features=[\"a\",\"b\"]"]),
+            Ok("{ server = { host = \"x\", port = 9090 }, url = \"x:9090\", features = [\"a\", \"b\"] }".to_string())
+        );
+        assert_eq!(
+            eval(DOCUMENT, &["server.host=\"y\"", "debug = server.port > 8000"]),
+            Ok("{ server = { host = \"y\", port = 8080 }, url = \"y:8080\", features = [\"a\"], debug = true }".to_string())
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            eval(DOCUMENT, &["server.port=1", "server.port="]),
+            Err("invalid override `server.port=': missing expression".to_string())
+        );
+        assert_eq!(
+            eval(DOCUMENT, &["9090"]),
+            Err("invalid override `9090': expected `path=expression'".to_string())
+        );
+        assert_eq!(
+            eval(DOCUMENT, &["a=1; b=2"]),
+            Err("invalid override `a=1; b=2': expected `path=expression'".to_string())
+        );
+        assert_eq!(
+            eval(DOCUMENT, &["server.port=1 + \"a\""]),
+            Err("invalid override `server.port=1 + \"a\"': unsupported operand types for `+': integer and string".to_string())
+        );
+        assert_eq!(
+            eval("[1]", &["a=1"]),
+            Err("cannot override fields of list".to_string())
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use std::rc::Rc;
+
+use super::value::Object;
+
+/// The objects whose fields are visible as identifiers.
+///
+/// The innermost object comes first, followed by the objects it was defined in.
+#[derive(Clone, Default)]
+pub struct Scope<'a> {
+    frame: Option<Rc<Frame<'a>>>,
+}
+
+struct Frame<'a> {
+    object: &'a Object<'a>,
+
+    /// The object that `object` extends, for `super`.
+    base: Option<&'a Object<'a>>,
+
+    /// Whether `object` only holds the variables of a comprehension, instead
+    /// of being an object that `self` and `root` can refer to.
+    variables: bool,
+
+    parent: Scope<'a>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new() -> Self {
+        Scope { frame: None }
+    }
+
+    /// Make a new scope with `object` as the innermost object.
+    ///
+    /// `base` is the object it extends, if any.
+    pub fn with_object(&self, object: &'a Object<'a>, base: Option<&'a Object<'a>>) -> Self {
+        self.with_frame(object, base, false)
+    }
+
+    /// Make a new scope with the variables of a comprehension.
+    pub fn with_variables(&self, variables: &'a Object<'a>) -> Self {
+        self.with_frame(variables, None, true)
+    }
+
+    fn with_frame(
+        &self,
+        object: &'a Object<'a>,
+        base: Option<&'a Object<'a>>,
+        variables: bool,
+    ) -> Self {
+        Scope {
+            frame: Some(Rc::new(Frame {
+                object,
+                base,
+                variables,
+                parent: self.clone(),
+            })),
+        }
+    }
+
+    /// Iterate over the objects, starting at the innermost one.
+    pub fn objects(&self) -> Objects<'a> {
+        Objects {
+            frame: self.frame.clone(),
+        }
+    }
+
+    /// The innermost object, for `self`, together with the object it extends,
+    /// for `super`.
+    pub fn innermost(&self) -> Option<(&'a Object<'a>, Option<&'a Object<'a>>)> {
+        self.object_frames().next()
+    }
+
+    /// The outermost object, for `root`.
+    pub fn outermost(&self) -> Option<&'a Object<'a>> {
+        self.object_frames().last().map(|(object, _)| object)
+    }
+
+    fn object_frames(&self) -> impl Iterator<Item = (&'a Object<'a>, Option<&'a Object<'a>>)> {
+        let mut frame = self.frame.clone();
+        std::iter::from_fn(move || {
+            while let Some(f) = frame.take() {
+                frame = f.parent.frame.clone();
+                if !f.variables {
+                    return Some((f.object, f.base));
+                }
+            }
+            None
+        })
+    }
+}
+
+pub struct Objects<'a> {
+    frame: Option<Rc<Frame<'a>>>,
+}
+
+impl<'a> Iterator for Objects<'a> {
+    type Item = &'a Object<'a>;
+    fn next(&mut self) -> Option<&'a Object<'a>> {
+        self.frame.take().map(|frame| {
+            self.frame = frame.parent.frame.clone();
+            frame.object
+        })
+    }
+}
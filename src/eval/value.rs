@@ -0,0 +1,335 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::mem;
+use std::time::Duration;
+
+use super::function::Function;
+use super::scope::Scope;
+use datetime::{Date, Timestamp};
+use error::{error, Error, Message};
+use expression::Expression;
+use unit::{format_byte_size, format_duration};
+
+/// The result of evaluating an expression.
+#[derive(Clone, Debug)]
+pub enum Value<'a> {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Double(f64),
+    Duration(Duration),
+    /// A number of bytes.
+    ByteSize(u64),
+    Date(Date),
+    Timestamp(Timestamp),
+    String(String),
+    List(Vec<Value<'a>>),
+    Object(&'a Object<'a>),
+    Function(Function),
+}
+
+impl<'a> Value<'a> {
+    /// The name of the type of this value, for in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Integer(_) => "integer",
+            Value::Double(_) => "float",
+            Value::Duration(_) => "duration",
+            Value::ByteSize(_) => "byte size",
+            Value::Date(_) => "date",
+            Value::Timestamp(_) => "timestamp",
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Object(_) => "object",
+            Value::Function(_) => "function",
+        }
+    }
+}
+
+/// An object, of which the fields are evaluated lazily.
+///
+/// Objects are allocated in an `Arena`, and are only handed out by reference.
+pub struct Object<'a> {
+    fields: Vec<Field<'a>>,
+    states: RefCell<Vec<State<'a>>>,
+    force_state: Cell<ForceState>,
+
+    /// The object to evaluate the fields in, if not this one.
+    context: Option<&'a Object<'a>>,
+}
+
+#[derive(Clone)]
+pub struct Field<'a> {
+    pub key: String,
+
+    /// Where the key of this field is defined.
+    pub location: &'a [u8],
+
+    /// Whether this is a local binding (`let`), which can be referred to by
+    /// name from within the object, but is not part of the object itself.
+    pub local: bool,
+
+    /// All definitions of this field, which are merged if there's more than
+    /// one.
+    pub(super) definitions: Vec<Definition<'a>>,
+}
+
+/// What a field evaluates to.
+#[derive(Clone)]
+pub(super) struct Definition<'a> {
+    pub expression: &'a Expression<'a>,
+
+    /// The scope the object was defined in.
+    pub scope: Scope<'a>,
+
+    /// The variables of the comprehension the field was defined in, if any.
+    ///
+    /// These take precedence over the fields of the object itself.
+    pub variables: Vec<&'a Object<'a>>,
+
+    /// The object that was extended with this definition, for `super`.
+    pub base: Option<&'a Object<'a>>,
+}
+
+enum State<'a> {
+    Unevaluated,
+    Evaluating,
+    Done(Value<'a>),
+}
+
+/// How far `Evaluator::force` got with an object.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) enum ForceState {
+    Unforced,
+    /// Its fields are being forced.
+    Forcing,
+    Forced,
+}
+
+impl<'a> Object<'a> {
+    pub(super) fn new(fields: Vec<Field<'a>>) -> Self {
+        Object {
+            states: RefCell::new(fields.iter().map(|_| State::Unevaluated).collect()),
+            fields,
+            force_state: Cell::new(ForceState::Unforced),
+            context: None,
+        }
+    }
+
+    /// Make an object with the fields of `object`, but evaluated as if they
+    /// were fields of `context`.
+    ///
+    /// This is what `super` refers to: the fields of the extended object, but
+    /// referring to the fields of the extending object.
+    pub(super) fn in_context(object: &Object<'a>, context: &'a Object<'a>) -> Self {
+        Object {
+            context: Some(context),
+            ..Object::new(object.fields.clone())
+        }
+    }
+
+    /// The object to evaluate the fields of this object in.
+    pub(super) fn context(&'a self) -> &'a Object<'a> {
+        self.context.unwrap_or(self)
+    }
+
+    /// Make an object of local bindings that already have a value.
+    pub(super) fn from_values(values: Vec<(String, &'a [u8], Value<'a>)>) -> Self {
+        let (fields, states) = values
+            .into_iter()
+            .map(|(key, location, value)| {
+                let field = Field {
+                    key,
+                    location,
+                    local: true,
+                    definitions: Vec::new(),
+                };
+                (field, State::Done(value))
+            })
+            .unzip();
+        Object {
+            fields,
+            states: RefCell::new(states),
+            force_state: Cell::new(ForceState::Unforced),
+            context: None,
+        }
+    }
+
+    /// The number of fields, not counting local bindings.
+    pub fn len(&self) -> usize {
+        self.fields.iter().filter(|f| !f.local).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All fields, including local bindings.
+    pub fn fields(&self) -> &[Field<'a>] {
+        &self.fields
+    }
+
+    /// The index of a field, if it exists and is not a local binding.
+    pub fn index_of(&self, key: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f.key == key && !f.local)
+    }
+
+    /// The index of a field or local binding.
+    pub(super) fn binding_index_of(&self, key: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f.key == key)
+    }
+
+    /// Get the value of a field.
+    ///
+    /// Only works on objects that have been fully evaluated, such as the ones
+    /// given by `Evaluator::evaluate`.
+    pub fn get(&self, key: &str) -> Option<Value<'a>> {
+        self.index_of(key).map(|i| self.value(i))
+    }
+
+    /// Iterate over the keys and values, skipping local bindings.
+    ///
+    /// Only works on objects that have been fully evaluated, such as the ones
+    /// given by `Evaluator::evaluate`.
+    pub fn iter<'b>(&'b self) -> impl Iterator<Item = (&'b str, Value<'a>)> + 'b {
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| !f.local)
+            .map(move |(i, f)| (&f.key[..], self.value(i)))
+    }
+
+    fn value(&self, index: usize) -> Value<'a> {
+        match &self.states.borrow()[index] {
+            State::Done(value) => value.clone(),
+            _ => panic!("field `{}' has not been evaluated", self.fields[index].key),
+        }
+    }
+
+    /// Get the value of a field, using `evaluate` to evaluate its definition
+    /// if that didn't happen yet.
+    ///
+    /// `reference` is the location that refers to the field, which is used in
+    /// the error when the field is (indirectly) defined in terms of itself.
+    pub(super) fn evaluate_field<F>(
+        &self,
+        index: usize,
+        reference: &'a [u8],
+        evaluate: F,
+    ) -> Result<Value<'a>, Error<'a>>
+    where
+        F: FnOnce(&[Definition<'a>]) -> Result<Value<'a>, Error<'a>>,
+    {
+        let field = &self.fields[index];
+        let state = mem::replace(&mut self.states.borrow_mut()[index], State::Evaluating);
+        match state {
+            State::Unevaluated => {}
+            State::Evaluating => {
+                let mut e = error(
+                    reference,
+                    format!("recursive definition of `{}'", field.key),
+                );
+                e.notes.push(Message {
+                    message: format!("`{}' is defined here", field.key),
+                    location: Some(field.location),
+                });
+                return Err(e);
+            }
+            State::Done(value) => {
+                self.states.borrow_mut()[index] = State::Done(value.clone());
+                return Ok(value);
+            }
+        }
+        let result = evaluate(&field.definitions);
+        self.states.borrow_mut()[index] = match &result {
+            Ok(value) => State::Done(value.clone()),
+            Err(_) => State::Unevaluated,
+        };
+        result
+    }
+
+    pub(super) fn force_state(&self) -> ForceState {
+        self.force_state.get()
+    }
+
+    pub(super) fn set_force_state(&self, state: ForceState) {
+        self.force_state.set(state);
+    }
+}
+
+impl<'a> fmt::Debug for Object<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let states = self.states.borrow();
+        let mut map = f.debug_map();
+        for (field, state) in self.fields.iter().zip(states.iter()) {
+            match state {
+                State::Done(value) => map.entry(&field.key, value),
+                _ => map.entry(&field.key, &format_args!("<unevaluated>")),
+            };
+        }
+        map.finish()
+    }
+}
+
+impl<'a> fmt::Display for Value<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Double(d) => write!(f, "{:?}", d),
+            Value::Duration(d) => write!(f, "{}", format_duration(*d)),
+            Value::ByteSize(b) => write!(f, "{}", format_byte_size(*b)),
+            Value::Date(d) => write!(f, "{}", d),
+            Value::Timestamp(t) => write!(f, "{}", t),
+            Value::String(s) => write_string(f, s),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (i, value) in list.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Function(function) => write!(f, "{}", function.name()),
+            Value::Object(object) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in object.iter().enumerate() {
+                    write!(f, "{}", if i == 0 { " " } else { ", " })?;
+                    if is_identifier(key) {
+                        write!(f, "{}", key)?;
+                    } else {
+                        write_string(f, key)?;
+                    }
+                    write!(f, " = {}", value)?;
+                }
+                write!(f, "{}}}", if object.is_empty() { "" } else { " " })
+            }
+        }
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04X}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
@@ -1,3 +1,7 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use datetime::{Date, Timestamp};
 use operator::{BinaryOperator, Operator, UnaryOperator};
 
 #[derive(Debug)]
@@ -8,7 +12,21 @@ pub enum Expression<'a> {
         op: Op<'a>,
         parenthesized: bool,
     },
-    Literal(Literal<'a>),
+    Literal {
+        source: &'a [u8],
+        value: Literal<'a>,
+    },
+}
+
+impl<'a> Expression<'a> {
+    /// The part of the source to point at in messages about this expression.
+    pub fn location(&self) -> &'a [u8] {
+        match *self {
+            Expression::Identifier(identifier) => identifier.as_bytes(),
+            Expression::Op { op_source, .. } => op_source.as_bytes(),
+            Expression::Literal { source, .. } => source,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -22,6 +40,12 @@ pub enum Op<'a> {
         rhs: Box<Expression<'a>>,
         lhs: Box<Expression<'a>>,
     },
+    /// `if condition then then_branch else else_branch`
+    Conditional {
+        condition: Box<Expression<'a>>,
+        then_branch: Box<Expression<'a>>,
+        else_branch: Box<Expression<'a>>,
+    },
 }
 
 impl<'a> Op<'a> {
@@ -29,15 +53,67 @@ impl<'a> Op<'a> {
         match self {
             &Op::UnaryOp { op, .. } => Operator::Unary(op),
             &Op::BinaryOp { op, .. } => Operator::Binary(op),
+            &Op::Conditional { .. } => Operator::Conditional,
         }
     }
 }
 
 #[derive(Debug)]
 pub enum Literal<'a> {
-    Integer(u64),
+    Null,
+    Bool(bool),
+    Integer(i64),
     Double(f64),
-    String(&'a str),
+    /// A number with a duration unit, such as `30s`.
+    Duration(Duration),
+    /// A number with a byte size unit, such as `64KiB`.
+    ByteSize(u64),
+    /// An RFC 3339 date, such as `2026-10-18`.
+    Date(Date),
+    /// An RFC 3339 timestamp, such as `2026-10-18T12:00:00Z`.
+    Timestamp(Timestamp),
+    String(Cow<'a, str>),
+    /// A string literal containing `${...}`.
+    Interpolation(Vec<Segment<'a>>),
     List(Vec<Box<Expression<'a>>>),
-    Object(Vec<Box<Expression<'a>>>, Vec<Box<Expression<'a>>>),
+    /// The `start:end` in `xs[start:end]`, where either bound can be left out.
+    Slice(Option<Box<Expression<'a>>>, Option<Box<Expression<'a>>>),
+    /// The keys, the values, and whether each element is a local binding
+    /// (`let`), which is left out of the resulting object.
+    Object(
+        Vec<Box<Expression<'a>>>,
+        Vec<Box<Expression<'a>>>,
+        Vec<bool>,
+    ),
+    /// `[element for ...]`
+    ListComprehension(Box<Expression<'a>>, Vec<Clause<'a>>),
+    /// `{ key = value for ... }`
+    ObjectComprehension(Box<Expression<'a>>, Box<Expression<'a>>, Vec<Clause<'a>>),
+}
+
+/// A part of a string literal with interpolations.
+#[derive(Debug)]
+pub enum Segment<'a> {
+    /// Text, with its escape sequences already processed.
+    Text {
+        source: &'a [u8],
+        value: Cow<'a, str>,
+    },
+    /// A `${...}` in the string. The source includes the `${` and `}`.
+    Interpolation {
+        source: &'a [u8],
+        expression: Box<Expression<'a>>,
+    },
+}
+
+/// A clause of a list or object comprehension.
+#[derive(Debug)]
+pub enum Clause<'a> {
+    /// `for a in iterable` or `for a, b in iterable`
+    For {
+        variables: Vec<&'a str>,
+        iterable: Box<Expression<'a>>,
+    },
+    /// `if condition`
+    If(Box<Expression<'a>>),
 }
@@ -1,3 +1,11 @@
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
+pub mod datetime;
+pub mod error;
+pub mod eval;
 pub mod expression;
 pub mod operator;
 pub mod parse;
+pub mod unit;
@@ -11,6 +11,7 @@ pub enum BinaryOperator {
     /* .  */ Dot,
     /* [] */ Index,
     /* () */ Call,
+    /* {} */ Extend,
     /* :  */ Colon,
     /* == */ Equal,
     /* != */ Inequal,
@@ -37,6 +38,7 @@ pub enum BinaryOperator {
 pub enum Operator {
     Unary(UnaryOperator),
     Binary(BinaryOperator),
+    /* if then else */ Conditional,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -51,7 +53,7 @@ fn get_precedence(op: Operator) -> i32 {
     use operator::BinaryOperator::*;
     use operator::Operator::*;
     match op {
-        Binary(Dot) | Binary(Colon) | Binary(Call) | Binary(Index) => 1,
+        Binary(Dot) | Binary(Colon) | Binary(Call) | Binary(Index) | Binary(Extend) => 1,
         Unary(_) => 2,
         Binary(Power) => 3,
         Binary(Times) | Binary(Divide) | Binary(Modulo) => 4,
@@ -64,12 +66,13 @@ fn get_precedence(op: Operator) -> i32 {
         Binary(BitOr) => 11,
         Binary(LogicalAnd) => 12,
         Binary(LogicalOr) => 13,
+        Conditional => 14,
     }
 }
 
 fn get_associativity(precedence: i32) -> Order {
     match precedence {
-        3 => Order::Right,         // **
+        3 | 14 => Order::Right,    // ** if
         7 | 8 => Order::Unordered, // > < >= <= != ==
         _ => Order::Left,
     }
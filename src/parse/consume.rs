@@ -6,6 +6,7 @@ pub trait Consume<'a> {
     fn consume(&mut self, what: &str) -> Option<&'a str>;
     fn consume_one_of(&mut self, chars: &str) -> Option<&'a str>;
     fn consume_while<F: FnMut(char) -> bool>(&mut self, f: F) -> &'a str;
+    fn consume_keyword(&mut self, keyword: &str) -> Option<&'a str>;
 }
 
 impl<'a> Consume<'a> for &'a [u8] {
@@ -40,6 +41,17 @@ impl<'a> Consume<'a> for &'a [u8] {
             .unwrap_or(self.len());
         unsafe { self.consume_str_n(n) }
     }
+    fn consume_keyword(&mut self, keyword: &str) -> Option<&'a str> {
+        let followed_by_identifier_char = matches!(
+            self.get(keyword.len()),
+            Some(&b) if b.is_ascii_alphanumeric() || b == b'_'
+        );
+        if followed_by_identifier_char {
+            None
+        } else {
+            self.consume(keyword)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -114,6 +126,23 @@ mod test {
         assert_eq!(m, b"");
     }
 
+    #[test]
+    fn keyword() {
+        let mut m = b"if x" as &[u8];
+        assert_eq!(m.consume_keyword("if"), Some("if"));
+        assert_eq!(m, b" x");
+        let mut m = b"iffy" as &[u8];
+        assert_eq!(m.consume_keyword("if"), None);
+        assert_eq!(m, b"iffy");
+        let mut m = b"if_" as &[u8];
+        assert_eq!(m.consume_keyword("if"), None);
+        let mut m = b"if(" as &[u8];
+        assert_eq!(m.consume_keyword("if"), Some("if"));
+        let mut m = b"if" as &[u8];
+        assert_eq!(m.consume_keyword("if"), Some("if"));
+        assert_eq!(m, b"");
+    }
+
     #[test]
     #[should_panic]
     fn panic() {
@@ -1,8 +1,8 @@
 use std::fmt::Write;
 
 use super::consume::Consume;
-use super::error::{error, Error, Message};
 use super::whitespace::skip_whitespace;
+use error::{error, Error, Message};
 
 /// Determines until what point should be parsed.
 /// An value of this type is given to the `parse_*` functions.
@@ -14,6 +14,11 @@ pub enum End<'a> {
     /// Only stop when this specific string is found.
     Specific(&'static str),
 
+    /// Only stop when this keyword is found.
+    ///
+    /// Unlike `Specific`, this doesn't match the start of a longer identifier.
+    Keyword(&'static str),
+
     /// Only stop when these brackets are matched.
     ///
     /// `MatchingBracket("(", ")")` looks for a `")"` to match the `"("`.
@@ -30,6 +35,7 @@ impl<'a> End<'a> {
         match self {
             End::EndOfFile => source.is_empty(),
             End::Specific(s) | End::MatchingBracket(_, s) => source.consume(s).is_some(),
+            End::Keyword(k) => source.consume_keyword(k).is_some(),
             End::ElementEnd => source.consume_one_of(",;\n").is_some(),
         }
     }
@@ -43,7 +49,9 @@ impl<'a> End<'a> {
     pub fn description(&self) -> String {
         match self {
             End::EndOfFile => "end of file".to_string(),
-            End::Specific(s) | End::MatchingBracket(_, s) => format!("`{}'", s),
+            End::Specific(s) | End::Keyword(s) | End::MatchingBracket(_, s) => {
+                format!("`{}'", s)
+            }
             End::ElementEnd => "newline or `,` or `;'".to_string(),
         }
     }
@@ -83,35 +91,49 @@ impl<'a> End<'a> {
 
     pub fn or_before(self, or_before: Self) -> OptionalEnd<'a> {
         OptionalEnd {
-            end: self,
-            or_before: Some(or_before),
+            end: Some(self),
+            or_before: vec![or_before],
         }
     }
 
     pub fn as_optional(self) -> OptionalEnd<'a> {
         OptionalEnd {
-            end: self,
-            or_before: None,
+            end: Some(self),
+            or_before: Vec::new(),
+        }
+    }
+
+    /// Stop right before this `End`, without consuming it.
+    pub fn before(self) -> OptionalEnd<'a> {
+        OptionalEnd {
+            end: None,
+            or_before: vec![self],
         }
     }
 }
 
+/// An `End` to consume, and/or `End`s to stop in front of.
 pub struct OptionalEnd<'a> {
-    pub end: End<'a>,
-    pub or_before: Option<End<'a>>,
+    pub end: Option<End<'a>>,
+    pub or_before: Vec<End<'a>>,
 }
 
 impl<'a> OptionalEnd<'a> {
+    /// Also stop right before this `End`.
+    pub fn or_before(mut self, or_before: End<'a>) -> Self {
+        self.or_before.push(or_before);
+        self
+    }
+
     pub fn parse(&self, source: &mut &'a [u8]) -> Result<bool, Error<'a>> {
-        skip_whitespace(
-            source,
-            match self.end {
-                End::ElementEnd => false,
-                _ => true,
-            },
-        );
-        let did_match =
-            self.end.consume(source) || self.or_before.map(|e| e.matches(*source)).unwrap_or(false);
+        let element_end = self
+            .end
+            .iter()
+            .chain(&self.or_before)
+            .any(|e| matches!(e, End::ElementEnd));
+        skip_whitespace(source, !element_end);
+        let did_match = self.end.map(|e| e.consume(source)).unwrap_or(false)
+            || self.or_before.iter().any(|e| e.matches(source));
         if !did_match && source.is_empty() {
             Err(self.error(*source))
         } else {
@@ -120,17 +142,18 @@ impl<'a> OptionalEnd<'a> {
     }
 
     pub fn description(&self) -> String {
-        let mut desc = self.end.description();
-        if let Some(e) = self.or_before {
+        let mut ends = self.end.iter().chain(&self.or_before);
+        let mut desc = ends.next().map(End::description).unwrap_or_default();
+        for e in ends {
             write!(&mut desc, " or {}", e.description()).unwrap();
         }
         desc
     }
 
     fn error(&self, source: &'a [u8]) -> Error<'a> {
-        match self.or_before {
-            None => self.end.error(source),
-            Some(_) => error(&source[..0], format!("expected {}", self.description())),
+        match (self.end, self.or_before.is_empty()) {
+            (Some(end), true) => end.error(source),
+            _ => error(&source[..0], format!("expected {}", self.description())),
         }
     }
 }
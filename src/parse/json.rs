@@ -0,0 +1,299 @@
+//! Parsing JSON documents into the same expressions as conftaal literals.
+
+use std::borrow::Cow;
+use std::char;
+
+use super::consume::Consume;
+use super::{string_from_utf8, unterminated_string};
+use error::{error, Error};
+use expression::{Expression, Literal};
+
+/// Parse a JSON document.
+///
+/// Objects, lists, strings, numbers, booleans and null become the literals
+/// they correspond to, such that they evaluate to conftaal values. Numbers
+/// without a fraction or exponent become integers if they fit.
+pub fn parse_json(source: &[u8]) -> Result<Expression<'_>, Error<'_>> {
+    let mut parser = JsonParser { source };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if !parser.source.is_empty() {
+        return Err(parser.unexpected("end of file"));
+    }
+    Ok(*value)
+}
+
+struct JsonParser<'a> {
+    source: &'a [u8],
+}
+
+impl<'a> JsonParser<'a> {
+    fn skip_whitespace(&mut self) {
+        self.source.consume_while(|c| " \t\r\n".contains(c));
+    }
+
+    /// An error pointing at the next character.
+    fn unexpected(&self, expected: &str) -> Error<'a> {
+        match self.source.first() {
+            None => error(self.source, format!("expected {}", expected)),
+            Some(_) => error(&self.source[..1], format!("expected {}", expected)),
+        }
+    }
+
+    fn literal(&self, start: &'a [u8], value: Literal<'a>) -> Box<Expression<'a>> {
+        Box::new(Expression::Literal {
+            source: &start[..start.len() - self.source.len()],
+            value,
+        })
+    }
+
+    fn parse_value(&mut self) -> Result<Box<Expression<'a>>, Error<'a>> {
+        self.skip_whitespace();
+        let start = self.source;
+        let value = match self.source.first() {
+            Some(b'{') => self.parse_object()?,
+            Some(b'[') => Literal::List(self.parse_list()?),
+            Some(b'"') => Literal::String(self.parse_string()?),
+            Some(b'-') | Some(b'0'..=b'9') => self.parse_number()?,
+            _ => {
+                if self.source.consume_keyword("true").is_some() {
+                    Literal::Bool(true)
+                } else if self.source.consume_keyword("false").is_some() {
+                    Literal::Bool(false)
+                } else if self.source.consume_keyword("null").is_some() {
+                    Literal::Null
+                } else {
+                    return Err(self.unexpected("JSON value"));
+                }
+            }
+        };
+        Ok(self.literal(start, value))
+    }
+
+    fn parse_object(&mut self) -> Result<Literal<'a>, Error<'a>> {
+        self.source.consume_n(1);
+        let (mut keys, mut values) = (Vec::new(), Vec::new());
+        self.skip_whitespace();
+        if self.source.consume("}").is_none() {
+            loop {
+                self.skip_whitespace();
+                let start = self.source;
+                if !self.source.starts_with(b"\"") {
+                    return Err(self.unexpected("string as object key"));
+                }
+                let key = Literal::String(self.parse_string()?);
+                keys.push(self.literal(start, key));
+                self.skip_whitespace();
+                if self.source.consume(":").is_none() {
+                    return Err(self.unexpected("`:'"));
+                }
+                values.push(self.parse_value()?);
+                self.skip_whitespace();
+                if self.source.consume("}").is_some() {
+                    break;
+                }
+                if self.source.consume(",").is_none() {
+                    return Err(self.unexpected("`,' or `}'"));
+                }
+            }
+        }
+        let locals = vec![false; keys.len()];
+        Ok(Literal::Object(keys, values, locals))
+    }
+
+    #[allow(clippy::vec_box)]
+    fn parse_list(&mut self) -> Result<Vec<Box<Expression<'a>>>, Error<'a>> {
+        self.source.consume_n(1);
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.source.consume("]").is_none() {
+            loop {
+                elements.push(self.parse_value()?);
+                self.skip_whitespace();
+                if self.source.consume("]").is_some() {
+                    break;
+                }
+                if self.source.consume(",").is_none() {
+                    return Err(self.unexpected("`,' or `]'"));
+                }
+            }
+        }
+        Ok(elements)
+    }
+
+    fn parse_string(&mut self) -> Result<Cow<'a, str>, Error<'a>> {
+        let start = self.source;
+        self.source.consume_n(1);
+        let mut value = Cow::Borrowed("");
+        loop {
+            let n = self
+                .source
+                .iter()
+                .position(|&b| b == b'"' || b == b'\\' || b < 0x20)
+                .ok_or_else(|| unterminated_string(start))?;
+            let chunk = string_from_utf8(self.source.consume_n(n))?;
+            if value.is_empty() {
+                value = Cow::Borrowed(chunk);
+            } else {
+                value.to_mut().push_str(chunk);
+            }
+            match self.source[0] {
+                b'"' => {
+                    self.source.consume_n(1);
+                    return Ok(value);
+                }
+                b'\\' => {
+                    let c = self.parse_escape_sequence()?;
+                    value.to_mut().push(c);
+                }
+                _ => {
+                    return Err(error(
+                        &self.source[..1],
+                        "control character in string".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn parse_escape_sequence(&mut self) -> Result<char, Error<'a>> {
+        let start = self.source;
+        self.source.consume_n(1);
+        let c = match self.source.first() {
+            Some(&c) => c,
+            None => return Err(error(&start[..1], "incomplete escape sequence".to_string())),
+        };
+        self.source.consume_n(1);
+        match c {
+            b'"' => Ok('"'),
+            b'\\' => Ok('\\'),
+            b'/' => Ok('/'),
+            b'b' => Ok('\x08'),
+            b'f' => Ok('\x0C'),
+            b'n' => Ok('\n'),
+            b'r' => Ok('\r'),
+            b't' => Ok('\t'),
+            b'u' => {
+                let high = self.parse_code_unit(start)?;
+                if (0xD800..0xDC00).contains(&high) && self.source.starts_with(b"\\u") {
+                    let rest = self.source;
+                    self.source.consume_n(2);
+                    let low = self.parse_code_unit(start)?;
+                    if (0xDC00..0xE000).contains(&low) {
+                        let c = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                        return Ok(char::from_u32(c).unwrap());
+                    }
+                    self.source = rest;
+                }
+                char::from_u32(high).ok_or_else(|| {
+                    error(
+                        &start[..start.len() - self.source.len()],
+                        "unpaired surrogate in escape sequence".to_string(),
+                    )
+                })
+            }
+            _ => Err(error(&start[..2], "invalid escape sequence".to_string())),
+        }
+    }
+
+    /// Parse the four hexadecimal digits of a `\u` escape sequence.
+    fn parse_code_unit(&mut self, start: &'a [u8]) -> Result<u32, Error<'a>> {
+        match self.source.get(..4) {
+            Some(digits) if digits.iter().all(u8::is_ascii_hexdigit) => {
+                let digits = unsafe { self.source.consume_str_n(4) };
+                Ok(u32::from_str_radix(digits, 16).unwrap())
+            }
+            _ => Err(error(
+                &start[..start.len() - self.source.len()],
+                "expected four hexadecimal digits after `\\u'".to_string(),
+            )),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Literal<'a>, Error<'a>> {
+        let start = self.source;
+        self.source.consume("-");
+        let integer = self.source.consume_while(|c| c.is_ascii_digit());
+        if integer.is_empty() || (integer.len() > 1 && integer.starts_with('0')) {
+            return Err(error(
+                &start[..start.len() - self.source.len()],
+                "invalid number".to_string(),
+            ));
+        }
+        let mut integral = true;
+        if self.source.consume(".").is_some() {
+            integral = false;
+            if self.source.consume_while(|c| c.is_ascii_digit()).is_empty() {
+                return Err(self.unexpected("digits after `.'"));
+            }
+        }
+        if self.source.consume_one_of("eE").is_some() {
+            integral = false;
+            self.source.consume_one_of("+-");
+            if self.source.consume_while(|c| c.is_ascii_digit()).is_empty() {
+                return Err(self.unexpected("digits in exponent"));
+            }
+        }
+        let number = &start[..start.len() - self.source.len()];
+        let number = string_from_utf8(number)?;
+        match number.parse() {
+            Ok(i) if integral => Ok(Literal::Integer(i)),
+            _ => Ok(Literal::Double(number.parse().unwrap())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(source: &str) -> Result<String, String> {
+        parse_json(source.as_bytes())
+            .map(|e| format!("{:?}", e))
+            .map_err(|e| e.message.message)
+    }
+
+    fn value(source: &str) -> String {
+        match parse_json(source.as_bytes()).unwrap() {
+            Expression::Literal { value, .. } => format!("{:?}", value),
+            other => panic!("not a literal: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(value("null"), "Null");
+        assert_eq!(value(" true "), "Bool(true)");
+        assert_eq!(value("-12"), "Integer(-12)");
+        assert_eq!(value("1.5e3"), "Double(1500.0)");
+        assert_eq!(value("9223372036854775808"), "Double(9.223372036854776e18)");
+        assert_eq!(
+            value(r#""a\n\u00e9\ud83d\ude00\/""#),
+            r#"String("a\né😀/")"#
+        );
+        assert_eq!(value("[]"), "List([])");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("{\"a\": 1,}"),
+            Err("expected string as object key".to_string())
+        );
+        assert_eq!(parse("[1 2]"), Err("expected `,' or `]'".to_string()));
+        assert_eq!(parse("01"), Err("invalid number".to_string()));
+        assert_eq!(parse("1."), Err("expected digits after `.'".to_string()));
+        assert_eq!(parse("\"\\x\""), Err("invalid escape sequence".to_string()));
+        assert_eq!(
+            parse("\"\\ud800\""),
+            Err("unpaired surrogate in escape sequence".to_string())
+        );
+        assert_eq!(parse("\"a"), Err("unterminated string literal".to_string()));
+        assert_eq!(
+            parse("\"a\nb\""),
+            Err("control character in string".to_string())
+        );
+        assert_eq!(parse("[1] 2"), Err("expected end of file".to_string()));
+        assert_eq!(parse("nul"), Err("expected JSON value".to_string()));
+    }
+}
@@ -1,23 +1,80 @@
 mod consume;
 pub mod end;
-mod error;
+pub mod json;
 mod whitespace; // TODO: make private
 
+use std::borrow::Cow;
+use std::char;
+use std::convert::TryFrom;
 use std::mem;
-use std::u64;
+use std::ops::Range;
+use std::str;
+use std::time::Duration;
 
 use self::consume::Consume;
 use self::end::{End, OptionalEnd};
-use self::error::{error, Error, Message};
 use self::whitespace::skip_whitespace;
-use expression::{Expression, Literal, Op};
+use datetime::{Date, Timestamp};
+use error::{error, Error, Message};
+use expression::{Clause, Expression, Literal, Op, Segment};
 use operator::{higher_precedence, BinaryOperator, Operator, Order, UnaryOperator};
+use unit::{self, Quantity};
 
 pub struct Parser<'a> {
     pub source: &'a [u8],
+
+    /// Whether an object can define the same key more than once, for
+    /// `DuplicateKeys::LastWins` and `DuplicateKeys::Merge`. Otherwise that
+    /// is an error. Computed keys are only checked when evaluating.
+    pub allow_duplicate_keys: bool,
 }
 
+/// The keys in a path like `a.b.c`, each with the source starting at that key.
+type KeyPath<'a> = Vec<(&'a [u8], Expression<'a>)>;
+
+/// The keys, values and local flags of an object literal.
+type ObjectElements<'a> = (
+    Vec<Box<Expression<'a>>>,
+    Vec<Box<Expression<'a>>>,
+    Vec<bool>,
+);
+
 impl<'a> Parser<'a> {
+    pub fn new(source: &'a [u8]) -> Self {
+        Parser {
+            source,
+            allow_duplicate_keys: false,
+        }
+    }
+
+    /// Parse a whole file.
+    ///
+    /// A file is either a list of `key = value` pairs without the surrounding
+    /// `{` and `}`, which is parsed as an object, or a single expression.
+    pub fn parse_document(&mut self) -> Result<Expression<'a>, Error<'a>> {
+        if self.starts_with_object_element() {
+            let start = self.source;
+            let (keys, values, locals) = self.parse_object(&End::EndOfFile)?;
+            Ok(self.literal(start, Literal::Object(keys, values, locals)))
+        } else {
+            self.parse_expression(&End::EndOfFile.as_optional())
+        }
+    }
+
+    /// Check (without consuming anything) if the source starts with `key =`
+    /// or `let key =`.
+    fn starts_with_object_element(&self) -> bool {
+        let mut p = Parser::new(self.source);
+        skip_whitespace(&mut p.source, true);
+        p.parse_let();
+        match p.parse_object_key_path() {
+            Ok(Some(_)) => {}
+            _ => return false,
+        }
+        skip_whitespace(&mut p.source, false);
+        p.source.starts_with(b"=") && !p.source.starts_with(b"==")
+    }
+
     pub fn parse_list(&mut self, end: &End<'a>) -> Result<Vec<Box<Expression<'a>>>, Error<'a>> {
         let mut elements = Vec::new();
         let element_end = End::Specific(",").or_before(*end);
@@ -29,30 +86,286 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub fn parse_object(
+    /// Parse a list literal or list comprehension, after the `[`.
+    fn parse_list_literal(&mut self, open: &'a str) -> Result<Literal<'a>, Error<'a>> {
+        let end = End::MatchingBracket(open, "]");
+        if end.parse(&mut self.source)? {
+            return Ok(Literal::List(Vec::new()));
+        }
+        let first = self.parse_expression(
+            &End::Specific(",")
+                .before()
+                .or_before(end)
+                .or_before(End::Keyword("for")),
+        )?;
+        if self.source.consume_keyword("for").is_some() {
+            let clauses = self.parse_comprehension(end)?;
+            return Ok(Literal::ListComprehension(Box::new(first), clauses));
+        }
+        self.source.consume(",");
+        let mut elements = vec![Box::new(first)];
+        elements.extend(self.parse_list(&end)?);
+        Ok(Literal::List(elements))
+    }
+
+    /// Parse the indices of `xs[...]`, after the `[`.
+    ///
+    /// This is either a list of expressions, or a slice `start:end` where
+    /// either bound can be left out. The `:` of a slice has the lowest
+    /// precedence, except that a `:` directly between two identifiers, like
+    /// `xs[std:n]`, is still the namespace operator.
+    fn parse_index(&mut self, open: &'a str) -> Result<Literal<'a>, Error<'a>> {
+        let end = End::MatchingBracket(open, "]");
+        let colon = End::Specific(":");
+        if end.parse(&mut self.source)? {
+            return Ok(Literal::List(Vec::new()));
+        }
+        let start = if colon.parse(&mut self.source)? {
+            None
+        } else {
+            let first = self
+                .parse_expression(&End::Specific(",").before().or_before(end).or_before(colon))?;
+            if self.source.consume(":").is_none() {
+                self.source.consume(",");
+                let mut indices = vec![Box::new(first)];
+                indices.extend(self.parse_list(&end)?);
+                return Ok(Literal::List(indices));
+            }
+            Some(Box::new(first))
+        };
+        let stop = if end.parse(&mut self.source)? {
+            None
+        } else {
+            Some(Box::new(self.parse_expression(&end.as_optional())?))
+        };
+        Ok(Literal::Slice(start, stop))
+    }
+
+    /// Parse an object literal or object comprehension, after the `{`.
+    fn parse_object_literal(&mut self, open: &'a str) -> Result<Literal<'a>, Error<'a>> {
+        let end = End::MatchingBracket(open, "}");
+        let mut elements = (Vec::new(), Vec::new(), Vec::new());
+        if end.parse(&mut self.source)? {
+            let (keys, values, locals) = elements;
+            return Ok(Literal::Object(keys, values, locals));
+        }
+        let element_start = self.source;
+        let (local, path, value) = self.parse_object_element(
+            &End::ElementEnd
+                .before()
+                .or_before(end)
+                .or_before(End::Keyword("for")),
+        )?;
+        let (keys, values, locals) = &mut elements;
+        if self.source.consume_keyword("for").is_some() {
+            if local {
+                return Err(error(
+                    &element_start[..3],
+                    "a comprehension cannot define a local binding".to_string(),
+                ));
+            }
+            add_object_element(
+                keys,
+                values,
+                locals,
+                path,
+                value,
+                false,
+                self.allow_duplicate_keys,
+            )?;
+            let clauses = self.parse_comprehension(end)?;
+            let (key, value) = (keys.pop().unwrap(), values.pop().unwrap());
+            return Ok(Literal::ObjectComprehension(key, value, clauses));
+        }
+        add_object_element(
+            keys,
+            values,
+            locals,
+            path,
+            value,
+            local,
+            self.allow_duplicate_keys,
+        )?;
+        End::ElementEnd.parse(&mut self.source)?;
+        let (keys, values, locals) = self.parse_more_object(&end, elements)?;
+        Ok(Literal::Object(keys, values, locals))
+    }
+
+    pub fn parse_object(&mut self, end: &End<'a>) -> Result<ObjectElements<'a>, Error<'a>> {
+        self.parse_more_object(end, (Vec::new(), Vec::new(), Vec::new()))
+    }
+
+    /// Parse the rest of an object, adding the elements to `elements`.
+    fn parse_more_object(
         &mut self,
         end: &End<'a>,
-    ) -> Result<(Vec<Box<Expression<'a>>>, Vec<Box<Expression<'a>>>), Error<'a>> {
-        let mut keys = Vec::new();
-        let mut values = Vec::new();
+        mut elements: ObjectElements<'a>,
+    ) -> Result<ObjectElements<'a>, Error<'a>> {
         let element_end = End::ElementEnd.or_before(*end);
         loop {
             if end.parse(&mut self.source)? {
-                return Ok((keys, values));
+                return Ok(elements);
+            }
+            let (local, path, value) = self.parse_object_element(&element_end)?;
+            let (keys, values, locals) = &mut elements;
+            add_object_element(
+                keys,
+                values,
+                locals,
+                path,
+                value,
+                local,
+                self.allow_duplicate_keys,
+            )?;
+        }
+    }
+
+    /// Parse a `key = value` or `let key = value` element of an object.
+    ///
+    /// Gives whether it is a local binding, the path of the key, and the value.
+    fn parse_object_element(
+        &mut self,
+        end: &OptionalEnd<'a>,
+    ) -> Result<(bool, KeyPath<'a>, Expression<'a>), Error<'a>> {
+        let local = self.parse_let();
+        let path = self.parse_object_key_path()?.ok_or_else(|| {
+            error(
+                &self.source[..0],
+                "expected identifier, string literal or `[' as object key".to_string(),
+            )
+        })?;
+        skip_whitespace(&mut self.source, false);
+        self.source
+            .consume("=")
+            .ok_or_else(|| error(&self.source[..0], "expected `='".to_string()))?;
+        let value = self.parse_expression(end)?;
+        let path = path
+            .into_iter()
+            .map(|(start, key)| (&start[..start.len() - self.source.len()], key))
+            .collect();
+        Ok((local, path, value))
+    }
+
+    /// Parse the clauses of a comprehension, after the first `for`, up to and
+    /// including `end`.
+    fn parse_comprehension(&mut self, end: End<'a>) -> Result<Vec<Clause<'a>>, Error<'a>> {
+        let clause_end = end
+            .before()
+            .or_before(End::Keyword("for"))
+            .or_before(End::Keyword("if"));
+        let mut clauses = Vec::new();
+        let mut is_for = true;
+        loop {
+            clauses.push(if is_for {
+                let variables = self.parse_comprehension_variables()?;
+                Clause::For {
+                    variables,
+                    iterable: Box::new(self.parse_expression(&clause_end)?),
+                }
+            } else {
+                Clause::If(Box::new(self.parse_expression(&clause_end)?))
+            });
+            if self.source.consume_keyword("for").is_some() {
+                is_for = true;
+            } else if self.source.consume_keyword("if").is_some() {
+                is_for = false;
+            } else {
+                end.parse(&mut self.source)?;
+                return Ok(clauses);
+            }
+        }
+    }
+
+    /// Parse the `a in` or `a, b in` of a `for` clause.
+    fn parse_comprehension_variables(&mut self) -> Result<Vec<&'a str>, Error<'a>> {
+        let mut variables = Vec::new();
+        loop {
+            skip_whitespace(&mut self.source, true);
+            let variable = self
+                .parse_identifier()
+                .ok_or_else(|| error(&self.source[..0], "expected identifier".to_string()))?;
+            variables.push(variable);
+            skip_whitespace(&mut self.source, true);
+            if variables.len() == 2 || self.source.consume(",").is_none() {
+                break;
+            }
+        }
+        self.source
+            .consume_keyword("in")
+            .ok_or_else(|| error(&self.source[..0], "expected `in'".to_string()))?;
+        Ok(variables)
+    }
+
+    /// Parse the `let` in front of a local binding in an object.
+    ///
+    /// `let` followed by `=` or `.` is a key, not a keyword.
+    fn parse_let(&mut self) -> bool {
+        let mut source = self.source;
+        if source.consume_keyword("let").is_none() {
+            return false;
+        }
+        skip_whitespace(&mut source, false);
+        if source.starts_with(b"=") || source.starts_with(b".") {
+            return false;
+        }
+        self.source = source;
+        true
+    }
+
+    /// Parse the key of an object element, which can be a path like `a.b.c`.
+    ///
+    /// Gives the keys in the path, together with the source starting at each
+    /// key.
+    fn parse_object_key_path(&mut self) -> Result<Option<KeyPath<'a>>, Error<'a>> {
+        let mut start = self.source;
+        let mut path = match self.parse_object_key()? {
+            Some(key) => vec![(start, key)],
+            None => return Ok(None),
+        };
+        loop {
+            skip_whitespace(&mut self.source, false);
+            if self.source.consume(".").is_none() {
+                return Ok(Some(path));
             }
-            let key = self.parse_identifier().ok_or_else(|| {
-                error(
-                    &self.source[..0],
-                    "expected identifier as object key".to_string(),
-                )
-            })?;
             skip_whitespace(&mut self.source, false);
-            self.source
-                .consume("=")
-                .ok_or_else(|| error(&self.source[..0], "expected `='".to_string()))?;
-            let value = self.parse_expression(&element_end)?;
-            keys.push(Box::new(Expression::Literal(Literal::String(key))));
-            values.push(Box::new(value));
+            start = self.source;
+            let key = if start.starts_with(b"[") {
+                None
+            } else {
+                self.parse_object_key()?
+            };
+            match key {
+                Some(key) => path.push((start, key)),
+                None => {
+                    return Err(error(
+                        &self.source[..0],
+                        "expected identifier or string literal after `.'".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Parse the key of an object element.
+    ///
+    /// A key is either an identifier or a string literal, which are both
+    /// returned as a `Literal::String`, or any expression between `[` and `]`,
+    /// which is returned as is, to be evaluated later.
+    fn parse_object_key(&mut self) -> Result<Option<Expression<'a>>, Error<'a>> {
+        let start = self.source;
+        if self.starts_with_string_literal() {
+            let string = self.parse_string_literal()?;
+            Ok(Some(self.literal(start, string)))
+        } else if let Some(identifier) = self.parse_identifier() {
+            Ok(Some(Expression::Literal {
+                source: identifier.as_bytes(),
+                value: Literal::String(Cow::Borrowed(identifier)),
+            }))
+        } else if let Some(open) = self.source.consume("[") {
+            self.parse_expression(&End::MatchingBracket(open, "]").as_optional())
+                .map(Some)
+        } else {
+            Ok(None)
         }
     }
 
@@ -89,6 +402,8 @@ impl<'a> Parser<'a> {
             return Ok(None);
         }
 
+        let start = self.source;
+
         if let Some(open) = self.source.consume("(") {
             let mut expr = self.parse_expression(&End::MatchingBracket(open, ")").as_optional())?;
             if let &mut Expression::Op {
@@ -99,7 +414,34 @@ impl<'a> Parser<'a> {
                 *parenthesized = true;
             }
             Ok(Some(expr))
+        } else if let Some(op_source) = self.source.consume_keyword("if") {
+            let condition = self.parse_expression(&End::Keyword("then").as_optional())?;
+            let then_branch = self.parse_expression(&End::Keyword("else").as_optional())?;
+            // Only parse an atom here. Any binary operators that follow will
+            // end up in the else branch, as `if' has the lowest precedence.
+            match self.parse_expression_atom(end)? {
+                None => Err(error(
+                    &self.source[..0],
+                    "missing expression after `else'".to_string(),
+                )),
+                Some(else_branch) => Ok(Some(Expression::Op {
+                    op_source,
+                    op: Op::Conditional {
+                        condition: Box::new(condition),
+                        then_branch: Box::new(then_branch),
+                        else_branch: Box::new(else_branch),
+                    },
+                    parenthesized: false,
+                })),
+            }
         } else if let Some((op_source, op)) = self.parse_unary_operator() {
+            // A `-` directly in front of a number is part of the literal, so
+            // that the most negative integer can be written down.
+            if op == UnaryOperator::Minus && !self.starts_with_date() {
+                if let Some(number) = self.parse_number(start, true)? {
+                    return Ok(Some(self.literal(start, number)));
+                }
+            }
             match self.parse_expression_atom(end)? {
                 None => Err(error(
                     &self.source[..0],
@@ -114,20 +456,27 @@ impl<'a> Parser<'a> {
                     parenthesized: false,
                 })),
             }
+        } else if self.starts_with_string_literal() {
+            let string = self.parse_string_literal()?;
+            Ok(Some(self.literal(start, string)))
         } else if let Some(identifier) = self.parse_identifier() {
-            Ok(Some(Expression::Identifier(identifier)))
-        } else if self.source.starts_with(b"\"") {
-            unimplemented!("string literals");
+            Ok(Some(match identifier {
+                "null" => self.literal(start, Literal::Null),
+                "true" => self.literal(start, Literal::Bool(true)),
+                "false" => self.literal(start, Literal::Bool(false)),
+                _ => Expression::Identifier(identifier),
+            }))
         } else if let Some(open) = self.source.consume("{") {
-            let (keys, values) = self.parse_object(&End::MatchingBracket(open, "}"))?;
-            Ok(Some(Expression::Literal(Literal::Object(keys, values))))
+            let object = self.parse_object_literal(open)?;
+            Ok(Some(self.literal(start, object)))
         } else if let Some(open) = self.source.consume("[") {
-            let list = self.parse_list(&End::MatchingBracket(open, "]"))?;
-            Ok(Some(Expression::Literal(Literal::List(list))))
-        } else if self.source.starts_with(b"\"") {
-            Ok(Some(Expression::Literal(self.parse_string_literal())))
-        } else if let Some(number) = self.parse_number()? {
-            Ok(Some(Expression::Literal(number)))
+            let list = self.parse_list_literal(open)?;
+            Ok(Some(self.literal(start, list)))
+        } else if self.starts_with_date() {
+            let date = self.parse_date_time()?;
+            Ok(Some(self.literal(start, date)))
+        } else if let Some(number) = self.parse_number(start, false)? {
+            Ok(Some(self.literal(start, number)))
         } else if self.source.starts_with(b"\\") {
             unimplemented!("lambdas");
         } else {
@@ -140,10 +489,12 @@ impl<'a> Parser<'a> {
         expr: &mut Expression<'a>,
         end: &OptionalEnd<'a>,
     ) -> Result<bool, Error<'a>> {
-        if end.parse(&mut self.source)? {
+        if !self.at_namespace_colon(expr) && end.parse(&mut self.source)? {
             return Ok(false);
         }
 
+        let start = self.source;
+
         let (op_source, op) = self.parse_binary_operator().ok_or_else(|| {
             error(
                 &self.source[..0],
@@ -152,12 +503,18 @@ impl<'a> Parser<'a> {
         })?;
 
         let rhs = match op {
-            BinaryOperator::Call => Expression::Literal(Literal::List(
-                self.parse_list(&End::MatchingBracket(op_source, ")"))?,
-            )),
-            BinaryOperator::Index => Expression::Literal(Literal::List(
-                self.parse_list(&End::MatchingBracket(op_source, "]"))?,
-            )),
+            BinaryOperator::Call => {
+                let list = self.parse_list(&End::MatchingBracket(op_source, ")"))?;
+                self.literal(start, Literal::List(list))
+            }
+            BinaryOperator::Index => {
+                let index = self.parse_index(op_source)?;
+                self.literal(start, index)
+            }
+            BinaryOperator::Extend => {
+                let object = self.parse_object_literal(op_source)?;
+                self.literal(start, object)
+            }
             BinaryOperator::Dot => self
                 .parse_identifier()
                 .map(|ident| Expression::Identifier(ident))
@@ -193,6 +550,23 @@ impl<'a> Parser<'a> {
         Ok(true)
     }
 
+    /// Check if a `:` directly follows an identifier at the end of `expr`,
+    /// and is directly followed by another, like `std:len`.
+    ///
+    /// Such a `:` is always the namespace operator, even where a `:` would
+    /// otherwise end the expression, as in a slice.
+    fn at_namespace_colon(&self, expr: &Expression<'a>) -> bool {
+        let after_identifier = last_identifier(expr).map_or(false, |id| {
+            id.as_bytes().as_ptr_range().end == self.source.as_ptr()
+        });
+        after_identifier
+            && self.source.starts_with(b":")
+            && self
+                .source
+                .get(1)
+                .map_or(false, |&b| b.is_ascii_alphabetic() || b == b'_')
+    }
+
     fn parse_unary_operator(&mut self) -> Option<(&'a str, UnaryOperator)> {
         use self::UnaryOperator::*;
         self.source
@@ -200,8 +574,8 @@ impl<'a> Parser<'a> {
             .and_then(|x| match x {
                 b'+' => Some(Plus),
                 b'-' => Some(Minus),
-                b'!' => Some(Complement),
-                b'~' => Some(LogicalNot),
+                b'!' => Some(LogicalNot),
+                b'~' => Some(Complement),
                 _ => None,
             })
             .map(|op| (unsafe { self.source.consume_str_n(1) }, op))
@@ -227,6 +601,7 @@ impl<'a> Parser<'a> {
             b'.' => Some(Dot),
             b'[' => Some(Index),
             b'(' => Some(Call),
+            b'{' => Some(Extend),
             b':' => Some(Colon),
             b'*' => Some(Times),
             b'/' => Some(Divide),
@@ -245,64 +620,714 @@ impl<'a> Parser<'a> {
         None
     }
 
-    fn parse_string_literal(&mut self) -> Literal<'a> {
-        unimplemented!("string literals");
+    fn starts_with_date(&self) -> bool {
+        matches_pattern(self.source, "dddd-dd-dd")
+    }
+
+    /// Parse an RFC 3339 date, such as `2026-10-18`, or timestamp, such as
+    /// `2026-10-18T12:00:00Z`.
+    fn parse_date_time(&mut self) -> Result<Literal<'a>, Error<'a>> {
+        let date_source = self.source.consume_n(10);
+        // Only used on digits, of which there are at most four.
+        let number = |source: &[u8], range: Range<usize>| -> u16 {
+            str::from_utf8(&source[range]).unwrap().parse().unwrap()
+        };
+        let date = Date::new(
+            number(date_source, 0..4),
+            number(date_source, 5..7) as u8,
+            number(date_source, 8..10) as u8,
+        )
+        .ok_or_else(|| error(date_source, "invalid date".to_string()))?;
+
+        if self.source.consume_one_of("Tt").is_none() {
+            return Ok(Literal::Date(date));
+        }
+
+        if !matches_pattern(self.source, "dd:dd:dd") {
+            return Err(error(
+                &self.source[..0],
+                "expected time (hh:mm:ss) after `T'".to_string(),
+            ));
+        }
+        let time_source = self.source.consume_n(8);
+        let (hour, minute, second) = (
+            number(time_source, 0..2) as u8,
+            number(time_source, 3..5) as u8,
+            number(time_source, 6..8) as u8,
+        );
+        if hour > 23 || minute > 59 || second > 59 {
+            return Err(error(time_source, "invalid time".to_string()));
+        }
+
+        let mut nanosecond = 0;
+        if self.source.consume(".").is_some() {
+            let digits = self.source.consume_while(|c| c.is_ascii_digit());
+            if digits.is_empty() {
+                return Err(error(&self.source[..0], "missing digits".to_string()));
+            }
+            // Anything beyond nanoseconds is dropped.
+            nanosecond = format!("{:0<9.9}", digits).parse().unwrap();
+        }
+
+        let offset_start = self.source;
+        let offset = match self.source.consume_one_of("Zz+-") {
+            Some("Z") | Some("z") => 0,
+            Some(sign) if matches_pattern(self.source, "dd:dd") => {
+                let offset_source = self.source.consume_n(5);
+                let hours = number(offset_source, 0..2) as i16;
+                let minutes = number(offset_source, 3..5) as i16;
+                if hours > 23 || minutes > 59 {
+                    return Err(error(
+                        &offset_start[..6],
+                        "invalid time zone offset".to_string(),
+                    ));
+                }
+                let offset = hours * 60 + minutes;
+                if sign == "-" {
+                    -offset
+                } else {
+                    offset
+                }
+            }
+            _ => {
+                return Err(error(
+                    &offset_start[..0],
+                    "expected `Z' or a time zone offset (+hh:mm or -hh:mm)".to_string(),
+                ))
+            }
+        };
+
+        Ok(Literal::Timestamp(Timestamp {
+            date,
+            hour,
+            minute,
+            second,
+            nanosecond,
+            offset,
+        }))
+    }
+
+    fn starts_with_string_literal(&self) -> bool {
+        self.source.starts_with(b"\"") || self.raw_string_hashes().is_some()
+    }
+
+    /// The number of `#`s of the raw string literal the source starts with,
+    /// if it starts with one.
+    fn raw_string_hashes(&self) -> Option<usize> {
+        if !self.source.starts_with(b"r") {
+            return None;
+        }
+        let hashes = self.source[1..].iter().take_while(|&&b| b == b'#').count();
+        if self.source[1 + hashes..].starts_with(b"\"") {
+            Some(hashes)
+        } else {
+            None
+        }
+    }
+
+    /// Parse a string literal of any kind.
+    ///
+    /// Gives a `Literal::Interpolation` if it contains any `${...}`, and a
+    /// `Literal::String` otherwise.
+    fn parse_string_literal(&mut self) -> Result<Literal<'a>, Error<'a>> {
+        if let Some(hashes) = self.raw_string_hashes() {
+            self.parse_raw_string_literal(hashes)
+        } else if self.source.starts_with(b"\"\"\"") {
+            self.parse_block_string_literal()
+        } else {
+            self.parse_quoted_string_literal()
+        }
+    }
+
+    /// Parse a raw string literal, like `r"..."` or `r#"..."#`.
+    ///
+    /// Backslashes have no special meaning in raw strings. The string ends at
+    /// the first `"` followed by as many `#`s as there were at the start.
+    fn parse_raw_string_literal(&mut self, hashes: usize) -> Result<Literal<'a>, Error<'a>> {
+        let start = self.source;
+        self.source.consume_n(hashes + 2);
+        let mut terminator = vec![b'"'];
+        terminator.resize(hashes + 1, b'#');
+        let n = self
+            .source
+            .windows(terminator.len())
+            .position(|w| w == &terminator[..])
+            .ok_or_else(|| unterminated_string(start))?;
+        let value = string_from_utf8(self.source.consume_n(n))?;
+        self.source.consume_n(terminator.len());
+        Ok(Literal::String(Cow::Borrowed(value)))
     }
 
-    fn parse_number(&mut self) -> Result<Option<Literal<'a>>, Error<'a>> {
-        let s = &mut self.source;
+    /// Parse a multiline string literal, starting at the opening `"""`.
+    ///
+    /// The string starts on the line after the opening `"""`, and ends
+    /// right before the closing `"""`. If that is on a line of its own, the
+    /// string ends with a newline. The indentation that all lines have in
+    /// common is removed. Escape sequences are not processed.
+    fn parse_block_string_literal(&mut self) -> Result<Literal<'a>, Error<'a>> {
+        let start = self.source;
+        self.source.consume_n(3);
+        self.source.consume_while(|c| c == ' ' || c == '\t');
+        if self.source.consume("\n").is_none() {
+            return Err(error(
+                &self.source[..0],
+                "expected newline after `\"\"\"'".to_string(),
+            ));
+        }
+        let n = self
+            .source
+            .windows(3)
+            .position(|w| w == b"\"\"\"")
+            .ok_or_else(|| unterminated_string(start))?;
+        let content = string_from_utf8(self.source.consume_n(n))?;
+        self.source.consume_n(3);
+        let is_blank = |line: &str| line.trim_start_matches(&[' ', '\t'][..]).is_empty();
+        let mut lines: Vec<&str> = content.split('\n').collect();
+        if let Some(last) = lines.last_mut() {
+            if is_blank(last) {
+                *last = "";
+            }
+        }
+        let mut indent: Option<&str> = None;
+        for line in lines.iter().filter(|line| !is_blank(line)) {
+            let whitespace = &line[..line.len() - line.trim_start_matches(&[' ', '\t'][..]).len()];
+            indent = Some(match indent {
+                None => whitespace,
+                Some(indent) => {
+                    let n = indent
+                        .bytes()
+                        .zip(whitespace.bytes())
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    &indent[..n]
+                }
+            });
+        }
+        let indent = indent.map_or(0, str::len);
+        let value = lines
+            .iter()
+            .map(|line| if is_blank(line) { "" } else { &line[indent..] })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(Literal::String(Cow::Owned(value)))
+    }
+
+    /// Parse a string literal in double quotes, starting at the `"`.
+    fn parse_quoted_string_literal(&mut self) -> Result<Literal<'a>, Error<'a>> {
+        let start = self.source;
+        self.source.consume_n(1);
+        let mut segments = Vec::new();
+        let mut value = Cow::Borrowed("");
+        let mut text_start = self.source;
+        loop {
+            let n = self
+                .source
+                .iter()
+                .position(|&b| b == b'"' || b == b'\\' || b == b'$')
+                .ok_or_else(|| unterminated_string(start))?;
+            let chunk = string_from_utf8(self.source.consume_n(n))?;
+            if value.is_empty() {
+                value = Cow::Borrowed(chunk);
+            } else {
+                value.to_mut().push_str(chunk);
+            }
+            let segment_end = self.source.starts_with(b"\"") || self.source.starts_with(b"${");
+            if segment_end && !value.is_empty() {
+                segments.push(Segment::Text {
+                    source: &text_start[..text_start.len() - self.source.len()],
+                    value: mem::replace(&mut value, Cow::Borrowed("")),
+                });
+            }
+            if self.source.consume("\"").is_some() {
+                return Ok(match segments.pop() {
+                    None => Literal::String(Cow::Borrowed("")),
+                    Some(Segment::Text { value, .. }) if segments.is_empty() => {
+                        Literal::String(value)
+                    }
+                    Some(segment) => {
+                        segments.push(segment);
+                        Literal::Interpolation(segments)
+                    }
+                });
+            }
+            let interpolation_start = self.source;
+            if let Some(open) = self.source.consume("${") {
+                let expression =
+                    self.parse_expression(&End::MatchingBracket(open, "}").as_optional())?;
+                segments.push(Segment::Interpolation {
+                    source: &interpolation_start[..interpolation_start.len() - self.source.len()],
+                    expression: Box::new(expression),
+                });
+                text_start = self.source;
+            } else if let Some(dollar) = self.source.consume("$") {
+                value.to_mut().push_str(dollar);
+            } else if let Some(c) = self.parse_escape_sequence()? {
+                value.to_mut().push(c);
+            }
+        }
+    }
 
-        if s.get(s.starts_with(b".") as usize)
+    /// Parse an escape sequence, starting at the backslash.
+    ///
+    /// Gives `None` for an escaped newline, which represents nothing.
+    fn parse_escape_sequence(&mut self) -> Result<Option<char>, Error<'a>> {
+        let start = self.source;
+        self.source.consume_n(1);
+        let c = match self.source.first() {
+            Some(&c) => c,
+            None => return Err(error(&start[..1], "incomplete escape sequence".to_string())),
+        };
+        self.source.consume_n(1);
+        let (codepoint, n_digits) = match c {
+            b'\n' => return Ok(None),
+            b'\\' => return Ok(Some('\\')),
+            b'$' => return Ok(Some('$')),
+            b'"' => return Ok(Some('"')),
+            b'\'' => return Ok(Some('\'')),
+            b'n' => return Ok(Some('\n')),
+            b'r' => return Ok(Some('\r')),
+            b't' => return Ok(Some('\t')),
+            b'a' => return Ok(Some('\x07')),
+            b'b' => return Ok(Some('\x08')),
+            b'e' => return Ok(Some('\x1B')),
+            b'f' => return Ok(Some('\x0C')),
+            b'v' => return Ok(Some('\x0B')),
+            b'0'..=b'7' => {
+                let mut codepoint = u32::from(c - b'0');
+                for _ in 0..2 {
+                    match self.source.first() {
+                        Some(&d @ b'0'..=b'7') => {
+                            self.source.consume_n(1);
+                            codepoint = codepoint * 8 + u32::from(d - b'0');
+                        }
+                        _ => break,
+                    }
+                }
+                if codepoint > 0o377 {
+                    return Err(error(
+                        &start[..start.len() - self.source.len()],
+                        "octal escape sequence out of range".to_string(),
+                    ));
+                }
+                (codepoint, 0)
+            }
+            b'x' => (0, 2),
+            b'u' => (0, 4),
+            b'U' => (0, 8),
+            _ => return Err(error(&start[..2], "invalid escape sequence".to_string())),
+        };
+        let mut codepoint = codepoint;
+        for _ in 0..n_digits {
+            let digit = self
+                .source
+                .first()
+                .and_then(|&d| (d as char).to_digit(16))
+                .ok_or_else(|| {
+                    error(
+                        &self.source[..self.source.len().min(1)],
+                        "expected hexadecimal digit (0-9, a-f, A-F)".to_string(),
+                    )
+                })?;
+            self.source.consume_n(1);
+            codepoint = codepoint * 16 + digit;
+        }
+        char::from_u32(codepoint).map(Some).ok_or_else(|| {
+            error(
+                &start[..start.len() - self.source.len()],
+                "invalid unicode codepoint".to_string(),
+            )
+        })
+    }
+
+    /// Parse a number literal, which started at `start`.
+    ///
+    /// `negative` is set when it was preceded by a `-`, which is then part of
+    /// the literal.
+    fn parse_number(
+        &mut self,
+        start: &'a [u8],
+        negative: bool,
+    ) -> Result<Option<Literal<'a>>, Error<'a>> {
+        if self
+            .source
+            .get(self.source.starts_with(b".") as usize)
             .map(|&b| (b as char).is_digit(10))
             != Some(true)
         {
             return Ok(None);
         }
 
-        let base = if let Some(b) = s.get(0..2).and_then(|x| match x {
+        let base = if let Some(b) = self.source.get(0..2).and_then(|x| match x {
             b"0x" | b"0X" => Some(16),
             b"0o" | b"0O" => Some(8),
             b"0b" | b"0B" => Some(2),
             _ => None,
         }) {
-            s.consume_n(2);
+            self.source.consume_n(2);
             b
         } else {
             10
         };
 
-        let integer_part = s.consume_while(|c| c.is_digit(base));
+        let integer_part = self.parse_digits(base)?;
 
-        let fractional_part = s
-            .consume(".")
-            .map(|_| s.consume_while(|c| c.is_digit(base)));
+        let fractional_part = match self.source.consume(".") {
+            Some(_) => Some(self.parse_digits(base)?),
+            None => None,
+        };
 
-        let exponent_part = s
+        // The exponent is always in decimal, also for hexadecimal literals.
+        let exponent_part = match self
+            .source
             .consume_one_of(if base == 16 { "pP" } else { "eE" })
-            .map(|_| {
-                (
-                    s.consume_one_of("+-") == Some("-"),
-                    s.consume_while(|c| c.is_digit(base)),
-                )
-            });
+        {
+            Some(_) => {
+                let negative_exponent = self.source.consume_one_of("+-") == Some("-");
+                let digits = self.parse_digits(10)?;
+                if digits.is_empty() {
+                    return Err(error(&self.source[..0], "missing exponent".to_string()));
+                }
+                Some((negative_exponent, digits))
+            }
+            None => None,
+        };
+
+        if integer_part.is_empty() && fractional_part.map_or(true, str::is_empty) {
+            return Err(error(integer_part.as_bytes(), "missing digits".to_string()));
+        }
+
+        let unit = match self.source.first() {
+            Some(b) if base == 10 && b.is_ascii_alphabetic() => {
+                let suffix = self.source.consume_while(|c| c.is_ascii_alphabetic());
+                match unit::lookup(suffix) {
+                    Some(unit) => Some(unit),
+                    None => {
+                        return Err(error(
+                            suffix.as_bytes(),
+                            format!("unknown unit `{}'", suffix),
+                        ))
+                    }
+                }
+            }
+            _ => None,
+        };
 
-        if exponent_part.is_none() && fractional_part.is_none() {
+        let literal = &start[..start.len() - self.source.len()];
+        let fraction = fractional_part.unwrap_or("").replace('_', "");
+        let exponent = exponent_part.map_or(0, |(negative_exponent, digits)| {
+            let e = digits.replace('_', "").parse().unwrap_or(i64::MAX);
+            if negative_exponent {
+                -e
+            } else {
+                e
+            }
+        });
+
+        if let Some((quantity, factor)) = unit {
+            if negative {
+                return Err(error(
+                    literal,
+                    format!("a {} cannot be negative", quantity.name()),
+                ));
+            }
+            let amount = scaled_amount(&integer_part.replace('_', ""), &fraction, exponent, factor)
+                .map_err(|too_large| {
+                    let message = if too_large {
+                        format!("{} too large", quantity.name())
+                    } else {
+                        format!(
+                            "{} must be a whole number of {}",
+                            quantity.name(),
+                            quantity.base_unit()
+                        )
+                    };
+                    error(literal, message)
+                })?;
+            Ok(Some(match quantity {
+                Quantity::Duration => Literal::Duration(Duration::from_nanos(amount)),
+                Quantity::ByteSize => Literal::ByteSize(amount),
+            }))
+        } else if exponent_part.is_none() && fractional_part.is_none() {
             // Integer
-            if integer_part.is_empty() {
-                return Err(error(integer_part.as_bytes(), "missing digits".to_string()));
-            }
-            match u64::from_str_radix(integer_part, base) {
-                Ok(i) => Ok(Some(Literal::Integer(i))),
-                Err(_) => Err(error(
-                    integer_part.as_bytes(),
-                    "integer too large".to_string(),
+            let magnitude = u64::from_str_radix(&integer_part.replace('_', ""), base).ok();
+            let value = match magnitude {
+                Some(m) if negative => 0i64.checked_sub_unsigned(m),
+                Some(m) => i64::try_from(m).ok(),
+                None => None,
+            };
+            match value {
+                Some(i) => Ok(Some(Literal::Integer(i))),
+                None => Err(error(
+                    &start[..start.len() - self.source.len()],
+                    "constant too large for 64-bit signed integer".to_string(),
                 )),
             }
         } else {
             // Float
-            unimplemented!("parsing float literals");
+            let integer = integer_part.replace('_', "");
+            let value = match base {
+                10 => format!(
+                    "{}.{}e{}",
+                    if integer.is_empty() { "0" } else { &integer },
+                    if fraction.is_empty() { "0" } else { &fraction },
+                    exponent
+                )
+                .parse()
+                .unwrap(),
+                16 if exponent_part.is_none() => {
+                    return Err(error(
+                        literal,
+                        "hexadecimal floating point literals need a `p' exponent".to_string(),
+                    ));
+                }
+                16 => hexadecimal_float(&integer, &fraction, exponent),
+                _ => {
+                    return Err(error(
+                        literal,
+                        format!(
+                            "floating point literals must be in decimal or hexadecimal, not in {}",
+                            if base == 8 { "octal" } else { "binary" }
+                        ),
+                    ));
+                }
+            };
+            Ok(Some(Literal::Double(if negative { -value } else { value })))
+        }
+    }
+
+    /// Consume the digits of a number literal, which may be separated by `_`.
+    fn parse_digits(&mut self, base: u32) -> Result<&'a str, Error<'a>> {
+        let digits = self.source.consume_while(|c| c.is_digit(base) || c == '_');
+        let bytes = digits.as_bytes();
+        for (i, &b) in bytes.iter().enumerate() {
+            let between_digits =
+                i > 0 && bytes[i - 1] != b'_' && bytes.get(i + 1).map_or(false, |&b| b != b'_');
+            if b == b'_' && !between_digits {
+                return Err(error(
+                    &bytes[i..i + 1],
+                    "`_' must be between digits".to_string(),
+                ));
+            }
+        }
+        Ok(digits)
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Make a literal expression of everything parsed since `start`.
+    fn literal(&self, start: &'a [u8], value: Literal<'a>) -> Expression<'a> {
+        Expression::Literal {
+            source: &start[..start.len() - self.source.len()],
+            value,
+        }
+    }
+}
+
+/// Check if `source` starts with something matching `pattern`, in which `d`
+/// stands for any decimal digit.
+fn matches_pattern(source: &[u8], pattern: &str) -> bool {
+    source.len() >= pattern.len()
+        && pattern.bytes().zip(source).all(|(p, &b)| match p {
+            b'd' => b.is_ascii_digit(),
+            p => b == p,
+        })
+}
+
+/// The value of `integer.fraction * 10^exponent * factor`, given in decimal
+/// digits.
+///
+/// Gives `Err(true)` if it doesn't fit in a `u64`, and `Err(false)` if it is
+/// not a whole number.
+fn scaled_amount(integer: &str, fraction: &str, exponent: i64, factor: u64) -> Result<u64, bool> {
+    let fraction = fraction.trim_end_matches('0');
+    let digits = format!("{}{}", integer, fraction);
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    let mantissa = digits
+        .parse::<u128>()
+        .ok()
+        .and_then(|m| m.checked_mul(u128::from(factor)))
+        .ok_or(true)?;
+    let scale = exponent.saturating_sub(fraction.len() as i64);
+    let power = |n: i64| u32::try_from(n).ok().and_then(|n| 10u128.checked_pow(n));
+    let amount = if scale >= 0 {
+        power(scale)
+            .and_then(|p| mantissa.checked_mul(p))
+            .ok_or(true)?
+    } else {
+        match power(-scale) {
+            Some(p) if mantissa % p == 0 => mantissa / p,
+            _ => return Err(false),
+        }
+    };
+    u64::try_from(amount).map_err(|_| true)
+}
+
+/// The value of `integer.fraction * 2^exponent`, given in hexadecimal digits.
+fn hexadecimal_float(integer: &str, fraction: &str, exponent: i64) -> f64 {
+    let mut mantissa: u64 = 0;
+    let mut exponent = exponent.saturating_sub(4 * fraction.len() as i64);
+    for c in integer.chars().chain(fraction.chars()) {
+        let digit = u64::from(c.to_digit(16).unwrap());
+        if mantissa >> 60 == 0 {
+            mantissa = mantissa << 4 | digit;
+        } else {
+            // Out of precision. Only remember whether something non-zero
+            // was dropped, for correct rounding.
+            exponent = exponent.saturating_add(4);
+            mantissa |= (digit != 0) as u64;
+        }
+    }
+    // Scale in steps, as 2^exponent itself might not fit in an f64.
+    let mut value = mantissa as f64;
+    let mut exponent = exponent.clamp(-2200, 2200) as i32;
+    while exponent != 0 {
+        let step = exponent.clamp(-1000, 1000);
+        value *= 2f64.powi(step);
+        exponent -= step;
+    }
+    value
+}
+
+/// The error for a string literal starting at `start` without an end.
+fn unterminated_string(start: &[u8]) -> Error<'_> {
+    let line_end = start.iter().position(|&b| b == b'\n');
+    error(
+        &start[..line_end.unwrap_or(start.len())],
+        "unterminated string literal".to_string(),
+    )
+}
+
+fn string_from_utf8(chunk: &[u8]) -> Result<&str, Error<'_>> {
+    str::from_utf8(chunk).map_err(|e| {
+        error(
+            &chunk[e.valid_up_to()..][..1],
+            "invalid UTF-8 in string literal".to_string(),
+        )
+    })
+}
+
+/// Add an element to the keys and values of an object.
+///
+/// For a path like `a.b.c`, `b.c` is added to the object literal defined as
+/// `a` if there is one, or to a new object literal otherwise.
+///
+/// `local` only applies to the first key of the path. Defining a key that is
+/// already defined is an error, unless `allow_duplicates` is set.
+fn add_object_element<'a>(
+    keys: &mut Vec<Box<Expression<'a>>>,
+    values: &mut Vec<Box<Expression<'a>>>,
+    locals: &mut Vec<bool>,
+    mut path: KeyPath<'a>,
+    value: Expression<'a>,
+    local: bool,
+    allow_duplicates: bool,
+) -> Result<(), Error<'a>> {
+    let (_, key) = path.remove(0);
+    if let Some(name) = static_key(&key) {
+        if let Some(i) = keys.iter().rposition(|k| static_key(k) == Some(name)) {
+            if locals[i] != local {
+                return Err(local_and_field(name, key.location(), keys[i].location()));
+            }
+            if path.is_empty() && !allow_duplicates {
+                return Err(duplicate_key(name, key.location(), keys[i].location()));
+            }
         }
     }
+    if path.is_empty() {
+        keys.push(Box::new(key));
+        values.push(Box::new(value));
+        locals.push(local);
+        return Ok(());
+    }
+    if let Some(name) = static_key(&key) {
+        if let Some(i) = keys.iter().rposition(|k| static_key(k) == Some(name)) {
+            return match values[i].as_mut() {
+                Expression::Literal {
+                    value: Literal::Object(keys, values, locals),
+                    ..
+                } => add_object_element(keys, values, locals, path, value, false, allow_duplicates),
+                _ => Err(Error {
+                    message: Message {
+                        message: format!(
+                            "cannot define fields in `{}', which is not an object",
+                            name
+                        ),
+                        location: Some(key.location()),
+                    },
+                    notes: vec![Message {
+                        message: format!("`{}' is defined here", name),
+                        location: Some(keys[i].location()),
+                    }],
+                }),
+            };
+        }
+    }
+    let source = path[0].0;
+    let mut object_keys = Vec::new();
+    let mut object_values = Vec::new();
+    let mut object_locals = Vec::new();
+    add_object_element(
+        &mut object_keys,
+        &mut object_values,
+        &mut object_locals,
+        path,
+        value,
+        false,
+        allow_duplicates,
+    )?;
+    keys.push(Box::new(key));
+    values.push(Box::new(Expression::Literal {
+        source,
+        value: Literal::Object(object_keys, object_values, object_locals),
+    }));
+    locals.push(local);
+    Ok(())
+}
+
+/// The error for a name that is defined both as a local binding and as a
+/// field of the same object.
+pub(crate) fn local_and_field<'a>(name: &str, location: &'a [u8], first: &'a [u8]) -> Error<'a> {
+    Error {
+        message: Message {
+            message: format!(
+                "`{}' is defined both as a local binding and as a field",
+                name
+            ),
+            location: Some(location),
+        },
+        notes: vec![Message {
+            message: format!("`{}' was first defined here", name),
+            location: Some(first),
+        }],
+    }
+}
+
+/// The error for a key that is defined more than once in the same object.
+pub(crate) fn duplicate_key<'a>(name: &str, location: &'a [u8], first: &'a [u8]) -> Error<'a> {
+    Error {
+        message: Message {
+            message: format!("duplicate key `{}'", name),
+            location: Some(location),
+        },
+        notes: vec![Message {
+            message: format!("`{}' was first defined here", name),
+            location: Some(first),
+        }],
+    }
+}
+
+/// The key as a string, if it is not a computed key.
+fn static_key<'a, 'b>(key: &'b Expression<'a>) -> Option<&'b str> {
+    match key {
+        Expression::Literal {
+            value: Literal::String(s),
+            ..
+        } => Some(s),
+        _ => None,
+    }
 }
 
 fn find_lhs<'a, 'b>(
@@ -320,9 +1345,10 @@ fn find_lhs<'a, 'b>(
                 op: e_op,
                 op_source: e_op_source,
                 parenthesized: false,
-            } if !is_lhs(e_op.op(), e_op_source, op, op_source)? => {
+            } if ends_with_conditional(e_op) || !is_lhs(e_op.op(), e_op_source, op, op_source)? => {
                 match e_op {
                     Op::UnaryOp { rhs, .. } | Op::BinaryOp { rhs, .. } => rhs.as_mut(),
+                    Op::Conditional { else_branch, .. } => else_branch.as_mut(),
                 }
             }
             e => return Ok(e),
@@ -330,6 +1356,40 @@ fn find_lhs<'a, 'b>(
     }
 }
 
+/// The identifier that `expr` ends with, if it isn't in parentheses.
+fn last_identifier<'a>(expr: &Expression<'a>) -> Option<&'a str> {
+    match expr {
+        Expression::Identifier(identifier) => Some(identifier),
+        Expression::Op {
+            op,
+            parenthesized: false,
+            ..
+        } => match op {
+            Op::UnaryOp { rhs, .. } | Op::BinaryOp { rhs, .. } => last_identifier(rhs),
+            Op::Conditional { else_branch, .. } => last_identifier(else_branch),
+        },
+        _ => None,
+    }
+}
+
+/// Check if the rightmost operand is an `if' without parentheses.
+///
+/// The else branch of such an `if' extends as far right as possible, so
+/// anything that follows belongs to it, regardless of precedence.
+fn ends_with_conditional(op: &Op) -> bool {
+    match op {
+        Op::Conditional { .. } => false,
+        Op::UnaryOp { rhs, .. } | Op::BinaryOp { rhs, .. } => match rhs.as_ref() {
+            Expression::Op {
+                op,
+                parenthesized: false,
+                ..
+            } => matches!(op, Op::Conditional { .. }) || ends_with_conditional(op),
+            _ => false,
+        },
+    }
+}
+
 fn is_lhs<'a>(
     left_op: Operator,
     left_op_source: &'a str,
@@ -358,3 +1418,87 @@ fn is_lhs<'a>(
         }),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn unary_operator(source: &str) -> UnaryOperator {
+        let expr = Parser::new(source.as_bytes())
+            .parse_expression(&End::EndOfFile.as_optional())
+            .unwrap();
+        match expr {
+            Expression::Op {
+                op: Op::UnaryOp { op, .. },
+                ..
+            } => op,
+            other => panic!("not a unary operator: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unary_operators() {
+        assert_eq!(unary_operator("-a"), UnaryOperator::Minus);
+        assert_eq!(unary_operator("!a"), UnaryOperator::LogicalNot);
+        assert_eq!(unary_operator("~a"), UnaryOperator::Complement);
+    }
+
+    fn string_literal(source: &str) -> Result<String, String> {
+        let expr = Parser::new(source.as_bytes())
+            .parse_expression(&End::EndOfFile.as_optional())
+            .map_err(|e| e.message.message)?;
+        match expr {
+            Expression::Literal {
+                value: Literal::String(s),
+                ..
+            } => Ok(s.into_owned()),
+            other => panic!("not a string literal: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn byte_escapes() {
+        assert_eq!(
+            string_literal(r#""\xFF \377 \0""#).unwrap(),
+            "\u{FF} \u{FF} \0"
+        );
+        assert_eq!(string_literal(r#""\40\0400""#).unwrap(), "  0");
+        assert_eq!(
+            string_literal(r#""\400""#).unwrap_err(),
+            "octal escape sequence out of range"
+        );
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let source = "{ a = 1; a = 2 }";
+        let e = Parser::new(source.as_bytes())
+            .parse_expression(&End::EndOfFile.as_optional())
+            .unwrap_err();
+        assert_eq!(e.message.message, "duplicate key `a'");
+        assert_eq!(e.message.location, Some(&source.as_bytes()[9..10]));
+        assert_eq!(e.notes[0].message, "`a' was first defined here");
+        assert_eq!(e.notes[0].location, Some(&source.as_bytes()[2..3]));
+
+        let document = |source: &str, allow_duplicate_keys| {
+            let mut parser = Parser::new(source.as_bytes());
+            parser.allow_duplicate_keys = allow_duplicate_keys;
+            parser
+                .parse_document()
+                .map(|_| ())
+                .map_err(|e| e.message.message)
+        };
+        assert_eq!(
+            document("a.b = 1; a.b = 2", false),
+            Err("duplicate key `b'".to_string())
+        );
+        assert_eq!(
+            document("a = { b = 1 }; a.b = 2", false),
+            Err("duplicate key `b'".to_string())
+        );
+        assert_eq!(document("a.b = 1; a.c = 2", false), Ok(()));
+        // Computed keys are only checked when evaluating.
+        assert_eq!(document("[a] = 1; [a] = 2", false), Ok(()));
+        assert_eq!(document("a = 1; a = 2", true), Ok(()));
+    }
+}
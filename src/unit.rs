@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+/// What a unit suffix on a number literal measures.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Quantity {
+    Duration,
+    ByteSize,
+}
+
+impl Quantity {
+    /// The name of this quantity, for in error messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            Quantity::Duration => "duration",
+            Quantity::ByteSize => "byte size",
+        }
+    }
+
+    /// The name of the smallest unit, in which values are stored.
+    pub fn base_unit(self) -> &'static str {
+        match self {
+            Quantity::Duration => "nanoseconds",
+            Quantity::ByteSize => "bytes",
+        }
+    }
+
+    fn units(self) -> &'static [(&'static str, u64)] {
+        match self {
+            Quantity::Duration => DURATION_UNITS,
+            Quantity::ByteSize => BYTE_SIZE_UNITS,
+        }
+    }
+}
+
+/// Duration units, with the number of nanoseconds they stand for.
+///
+/// Ordered from large to small.
+const DURATION_UNITS: &[(&str, u64)] = &[
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+/// Byte size units, with the number of bytes they stand for.
+///
+/// Ordered from large to small.
+const BYTE_SIZE_UNITS: &[(&str, u64)] = &[
+    ("PiB", 1 << 50),
+    ("PB", 1_000_000_000_000_000),
+    ("TiB", 1 << 40),
+    ("TB", 1_000_000_000_000),
+    ("GiB", 1 << 30),
+    ("GB", 1_000_000_000),
+    ("MiB", 1 << 20),
+    ("MB", 1_000_000),
+    ("KiB", 1 << 10),
+    ("KB", 1_000),
+    ("B", 1),
+];
+
+/// Look up a unit suffix, such as `ms` or `KiB`.
+///
+/// Gives what it measures, and how many nanoseconds or bytes it stands for.
+pub fn lookup(suffix: &str) -> Option<(Quantity, u64)> {
+    [Quantity::Duration, Quantity::ByteSize]
+        .iter()
+        .flat_map(|&q| {
+            q.units()
+                .iter()
+                .map(move |&(name, factor)| (q, name, factor))
+        })
+        .find(|&(_, name, _)| name == suffix)
+        .map(|(q, _, factor)| (q, factor))
+}
+
+/// Write down an amount using the largest unit that represents it exactly.
+fn format(quantity: Quantity, amount: u128) -> String {
+    let units = quantity.units();
+    let &(name, factor) = units
+        .iter()
+        .find(|&&(_, factor)| amount % u128::from(factor) == 0 && amount != 0)
+        .unwrap_or(&units[units.len() - 1]);
+    format!("{}{}", amount / u128::from(factor), name)
+}
+
+/// Write down a duration as a literal, such as `90s` or `250ms`.
+pub fn format_duration(duration: Duration) -> String {
+    format(Quantity::Duration, duration.as_nanos())
+}
+
+/// Write down a byte size as a literal, such as `64KiB` or `1500MB`.
+pub fn format_byte_size(bytes: u64) -> String {
+    format(Quantity::ByteSize, u128::from(bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_duration(Duration::from_secs(90)), "90s");
+        assert_eq!(format_duration(Duration::from_secs(5400)), "90m");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1500ms");
+        assert_eq!(format_duration(Duration::from_secs(0)), "0ns");
+        assert_eq!(format_byte_size(65536), "64KiB");
+        assert_eq!(format_byte_size(1_500_000_000), "1500MB");
+        assert_eq!(format_byte_size(3), "3B");
+        assert_eq!(format_byte_size(0), "0B");
+    }
+}
@@ -2,9 +2,9 @@ extern crate conftaal;
 
 use std::{env, fs};
 
-use conftaal::expression::{Expression, Literal, Op};
-use conftaal::parse::end::End;
+use conftaal::expression::{Clause, Expression, Literal, Op, Segment};
 use conftaal::parse::Parser;
+use conftaal::unit::{format_byte_size, format_duration};
 
 fn format_list(list: &Vec<Box<Expression>>) -> String {
     let mut s = "(list".to_string();
@@ -16,6 +16,60 @@ fn format_list(list: &Vec<Box<Expression>>) -> String {
     s
 }
 
+fn format_keys(keys: &Vec<Box<Expression>>, locals: &[bool]) -> String {
+    let mut s = "(list".to_string();
+    for (e, &local) in keys.iter().zip(locals) {
+        s += " ";
+        if local {
+            s += &format!("(let {})", format(e))[..];
+        } else {
+            s += &format(e)[..];
+        }
+    }
+    s += ")";
+    s
+}
+
+fn format_clauses(clauses: &[Clause]) -> String {
+    let mut s = String::new();
+    for clause in clauses {
+        s += &match clause {
+            Clause::For {
+                variables,
+                iterable,
+            } => format!(" (for {} {})", variables.join(","), format(iterable)),
+            Clause::If(condition) => format!(" (if {})", format(condition)),
+        }[..];
+    }
+    s
+}
+
+fn format_string(s: &str) -> String {
+    format!("str:\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Format a float in hexadecimal, like `%a` in C.
+fn format_double(f: f64) -> String {
+    if f.is_nan() {
+        return "nan".to_string();
+    }
+    let sign = if f.is_sign_negative() { "-" } else { "" };
+    if f.is_infinite() {
+        return format!("{}inf", sign);
+    }
+    let bits = f.to_bits();
+    let mantissa = bits & ((1 << 52) - 1);
+    let (lead, exponent) = match (bits >> 52) & 0x7ff {
+        0 if mantissa == 0 => (0, 0),
+        0 => (0, -1022),
+        e => (1, e as i64 - 1023),
+    };
+    let digits = format!("{:013x}", mantissa);
+    let digits = digits.trim_end_matches('0');
+    let point = if digits.is_empty() { "" } else { "." };
+    format!("{}0x{}{}{}p{:+}", sign, lead, point, digits, exponent)
+}
+
 fn format(e: &Expression) -> String {
     use Expression::*;
     use Literal::*;
@@ -39,15 +93,90 @@ fn format(e: &Expression) -> String {
             format(lhs.as_ref()),
             format(rhs.as_ref())
         ),
-        Literal(List(elements)) => format_list(elements),
-        Literal(Object(keys, values)) => format!(
+        Op {
+            op:
+                Conditional {
+                    condition,
+                    then_branch,
+                    else_branch,
+                },
+            ..
+        } => format!(
+            "(if {} {} {})",
+            format(condition.as_ref()),
+            format(then_branch.as_ref()),
+            format(else_branch.as_ref())
+        ),
+        Literal {
+            value: List(elements),
+            ..
+        } => format_list(elements),
+        Literal {
+            value: Slice(start, end),
+            ..
+        } => {
+            let bound =
+                |e: &Option<Box<Expression>>| e.as_ref().map_or("_".to_string(), |e| format(e));
+            format!("(slice {} {})", bound(start), bound(end))
+        }
+        Literal {
+            value: Object(keys, values, locals),
+            ..
+        } => format!(
             "(object keys={} values={})",
-            format_list(keys),
+            format_keys(keys, locals),
             format_list(values)
         ),
-        Literal(String(s)) => format!("str:{:?}", s),
-        Literal(Integer(i)) => format!("int:{}", i),
-        Literal(Double(f)) => format!("float:{}", f),
+        Literal {
+            value: ListComprehension(element, clauses),
+            ..
+        } => format!("(list-for {}{})", format(element), format_clauses(clauses)),
+        Literal {
+            value: ObjectComprehension(key, value, clauses),
+            ..
+        } => format!(
+            "(object-for {} {}{})",
+            format(key),
+            format(value),
+            format_clauses(clauses)
+        ),
+        Literal {
+            value: String(s), ..
+        } => format_string(s),
+        Literal {
+            value: Interpolation(segments),
+            ..
+        } => {
+            let mut s = "(interpolation".to_string();
+            for segment in segments {
+                s += " ";
+                s += &match segment {
+                    Segment::Text { value, .. } => format_string(value),
+                    Segment::Interpolation { expression, .. } => format(expression),
+                }[..];
+            }
+            s += ")";
+            s
+        }
+        Literal { value: Null, .. } => "null".to_string(),
+        Literal { value: Bool(b), .. } => format!("bool:{}", b),
+        Literal {
+            value: Integer(i), ..
+        } => format!("int:{}", i),
+        Literal {
+            value: Double(f), ..
+        } => format!("float:{}", format_double(*f)),
+        Literal {
+            value: Duration(d), ..
+        } => format!("duration:{}", format_duration(*d)),
+        Literal {
+            value: ByteSize(b), ..
+        } => format!("bytes:{}", format_byte_size(*b)),
+        Literal { value: Date(d), .. } => format!("date:{}", d),
+        Literal {
+            value: Timestamp(t),
+            ..
+        } => format!("timestamp:{}", t),
     }
 }
 
@@ -55,9 +184,9 @@ fn main() {
     for filename in env::args().skip(1) {
         let source = fs::read(filename).unwrap();
 
-        let mut parser = Parser { source: &source };
+        let mut parser = Parser::new(&source);
 
-        match parser.parse_expression(&End::EndOfFile.as_optional()) {
+        match parser.parse_document() {
             Ok(expr) => println!("{}", format(&expr)),
             Err(e) => println!("Error: {:#?}", e),
         };